@@ -1,11 +1,10 @@
-use crate::http;
-use crate::{Backend, Build, Error};
+use crate::cpu;
+use crate::{Backend, Build, Error, Platform};
 
-use sipper::Straw;
-use tokio::io::AsyncWrite;
+use std::fmt;
 
 /// A component of a `llama-server` instance.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Artifact {
     /// The server binary.
     Server,
@@ -13,39 +12,209 @@ pub enum Artifact {
     Backend(Backend),
 }
 
-impl Artifact {
-    pub(crate) fn download<W: AsyncWrite + Unpin>(
-        self,
-        build: Build,
-        writer: &mut W,
-    ) -> impl Straw<(), http::Progress, Error> {
-        let release_url = build.url();
-
-        http::download(
-            match self {
-                Artifact::Server => format!("{release_url}/llama-server-{build}-{PLATFORM}.zip"),
-                Artifact::Backend(backend) => {
-                    let name = match backend {
-                        Backend::Cuda => "cuda",
-                        Backend::Hip => "hip",
-                    };
-
-                    format!("{release_url}/backend-{name}-{build}-{PLATFORM}.zip")
-                }
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Server => f.write_str("server"),
+            Self::Backend(backend) => write!(f, "{backend} backend"),
+        }
+    }
+}
+
+/// An [`Artifact`] published for a specific platform.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Asset {
+    /// The [`Artifact`] of the [`Asset`].
+    pub artifact: Artifact,
+    /// The [`Platform`] of the [`Asset`].
+    pub platform: Platform,
+    /// The CPU [`Variant`](cpu::Variant) of the [`Asset`].
+    ///
+    /// Only the server binary is published in multiple variants.
+    pub cpu: cpu::Variant,
+    /// The major version of the CUDA runtime bundled in the [`Asset`], if versioned.
+    ///
+    /// Only the CUDA backend is published in multiple runtime versions.
+    pub cuda: Option<u32>,
+}
+
+impl Asset {
+    /// Returns the [`Asset`] of the given [`Artifact`] for the current platform.
+    ///
+    /// Fails with [`Error::UnsupportedPlatform`] if the current platform is not supported.
+    pub fn current(artifact: Artifact) -> Result<Self, Error> {
+        Ok(Self::new(artifact, Platform::detect()?))
+    }
+
+    /// Returns the portable [`Asset`] of the given [`Artifact`] for the given [`Platform`].
+    pub fn new(artifact: Artifact, platform: Platform) -> Self {
+        Self {
+            artifact,
+            platform,
+            cpu: cpu::Variant::Portable,
+            cuda: None,
+        }
+    }
+
+    /// Returns the [`Asset`] with the given CPU [`Variant`](cpu::Variant).
+    pub fn cpu(self, cpu: cpu::Variant) -> Self {
+        Self { cpu, ..self }
+    }
+
+    /// Returns the [`Asset`] bundling the given major version of the CUDA runtime.
+    pub fn cuda(self, major: u32) -> Self {
+        Self {
+            cuda: Some(major),
+            ..self
+        }
+    }
+
+    /// Returns the file name of the [`Asset`] in the given [`Build`].
+    pub fn file_name(&self, build: Build) -> String {
+        let platform = &self.platform;
+
+        match self.artifact {
+            Artifact::Server if self.cpu != cpu::Variant::Portable => {
+                format!("llama-server-{build}-{platform}-{cpu}.zip", cpu = self.cpu)
+            }
+            Artifact::Server => format!("llama-server-{build}-{platform}.zip"),
+            Artifact::Backend(backend) => match self.cuda {
+                Some(major) => format!(
+                    "backend-{name}-{build}-{platform}-cuda{major}.zip",
+                    name = backend.name()
+                ),
+                None => format!(
+                    "backend-{name}-{build}-{platform}.zip",
+                    name = backend.name()
+                ),
             },
-            writer,
-        )
+        }
+    }
+
+    /// Parses the [`Asset`] with the given file name in the given [`Build`], if any.
+    pub fn parse(build: Build, file_name: &str) -> Option<Self> {
+        let stem = file_name.strip_suffix(".zip")?;
+
+        let (artifact, platform, cpu, cuda) =
+            if let Some(platform) = stem.strip_prefix(&format!("llama-server-{build}-")) {
+                cpu::Variant::ALL
+                    .iter()
+                    .copied()
+                    .filter(|cpu| *cpu != cpu::Variant::Portable)
+                    .find_map(|cpu| {
+                        let platform = platform.strip_suffix(&format!("-{cpu}"))?;
+
+                        Some((Artifact::Server, platform, cpu, None))
+                    })
+                    .unwrap_or((Artifact::Server, platform, cpu::Variant::Portable, None))
+            } else {
+                let (name, platform) = stem
+                    .strip_prefix("backend-")?
+                    .split_once(&format!("-{build}-"))?;
+
+                let backend = Backend::ALL
+                    .iter()
+                    .copied()
+                    .find(|backend| backend.name() == name)?;
+
+                let (platform, cuda) = match platform.rsplit_once("-cuda") {
+                    Some((platform, major)) if backend == Backend::Cuda => {
+                        (platform, Some(major.parse().ok()?))
+                    }
+                    _ => (platform, None),
+                };
+
+                (
+                    Artifact::Backend(backend),
+                    platform,
+                    cpu::Variant::Portable,
+                    cuda,
+                )
+            };
+
+        Some(Self {
+            cuda,
+            ..Self::new(artifact, platform.parse().ok()?).cpu(cpu)
+        })
     }
 }
 
-#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-const PLATFORM: &str = "linux-x64";
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(build: Build, asset: Asset) {
+        let file_name = asset.file_name(build);
+
+        assert_eq!(
+            Asset::parse(build, &file_name),
+            Some(asset),
+            "parse {file_name}"
+        );
+    }
+
+    #[test]
+    fn it_round_trips_file_names() {
+        let build = Build::locked(6730);
+
+        for &platform in Platform::ALL {
+            for &cpu in cpu::Variant::ALL {
+                assert_round_trip(build, Asset::new(Artifact::Server, platform).cpu(cpu));
+            }
+
+            for &backend in Backend::ALL {
+                assert_round_trip(build, Asset::new(Artifact::Backend(backend), platform));
+            }
 
-#[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-const PLATFORM: &str = "macos-x64";
+            for major in [11, 12, 13] {
+                assert_round_trip(
+                    build,
+                    Asset::new(Artifact::Backend(Backend::Cuda), platform).cuda(major),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_parses_published_file_names() {
+        let build = Build::locked(6730);
 
-#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-const PLATFORM: &str = "macos-arm64";
+        assert_eq!(
+            Asset::parse(build, "llama-server-b6730-linux-x64-avx2.zip"),
+            Some(Asset::new(Artifact::Server, Platform::LinuxX64).cpu(cpu::Variant::Avx2))
+        );
+        assert_eq!(
+            Asset::parse(build, "backend-cuda-b6730-windows-x64-cuda12.zip"),
+            Some(Asset::new(Artifact::Backend(Backend::Cuda), Platform::WindowsX64).cuda(12))
+        );
+        assert_eq!(
+            Asset::parse(build, "backend-hip-b6730-linux-x64.zip"),
+            Some(Asset::new(
+                Artifact::Backend(Backend::Hip),
+                Platform::LinuxX64
+            ))
+        );
 
-#[cfg(all(target_os = "windows", target_arch = "x86_64"))]
-const PLATFORM: &str = "windows-x64";
+        assert_eq!(
+            Asset::parse(build, "llama-server-b6729-linux-x64.zip"),
+            None
+        );
+        assert_eq!(
+            Asset::parse(build, "llama-server-b6730-linux-x64-sse4.zip"),
+            None
+        );
+        assert_eq!(
+            Asset::parse(build, "backend-hip-b6730-linux-x64-cuda12.zip"),
+            None
+        );
+        assert_eq!(
+            Asset::parse(build, "backend-cuda-b6730-linux-x64-cudax.zip"),
+            None
+        );
+        assert_eq!(Asset::parse(build, "backend-tpu-b6730-linux-x64.zip"), None);
+        assert_eq!(
+            Asset::parse(build, "llama-server-b6730-linux-x64.tar.gz"),
+            None
+        );
+    }
+}
@@ -1,6 +1,16 @@
 //! Pick your preferred compute backends.
+mod driver;
+mod probe;
+
+pub use driver::Driver;
+pub use probe::Probe;
+
+use crate::Platform;
+
 use bitflags::bitflags;
 
+use std::fmt;
+
 /// A compute backend.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Backend {
@@ -8,6 +18,82 @@ pub enum Backend {
     Cuda,
     /// The AMD HIP backend.
     Hip,
+    /// The Vulkan backend.
+    Vulkan,
+    /// The Intel SYCL backend.
+    Sycl,
+    /// The OpenCL backend.
+    OpenCl,
+    /// The CPU backend.
+    ///
+    /// It is built into the server binary.
+    Cpu,
+    /// The Apple Metal backend.
+    ///
+    /// It is built into the server binary.
+    Metal,
+}
+
+impl Backend {
+    /// All the compute backends.
+    pub const ALL: &[Self] = &[
+        Self::Cuda,
+        Self::Hip,
+        Self::Vulkan,
+        Self::Sycl,
+        Self::OpenCl,
+        Self::Cpu,
+        Self::Metal,
+    ];
+
+    /// Returns the name of the [`Backend`] used in published file names (e.g. `cuda`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cuda => "cuda",
+            Self::Hip => "hip",
+            Self::Vulkan => "vulkan",
+            Self::Sycl => "sycl",
+            Self::OpenCl => "opencl",
+            Self::Cpu => "cpu",
+            Self::Metal => "metal",
+        }
+    }
+
+    /// Returns whether the [`Backend`] is built into the server binary, and therefore
+    /// does not need to be downloaded.
+    pub fn is_builtin(self) -> bool {
+        matches!(self, Self::Cpu | Self::Metal)
+    }
+
+    /// Returns whether the [`Backend`] is available in the given [`Platform`].
+    pub fn is_available_on(self, platform: Platform) -> bool {
+        match self {
+            Self::Cuda | Self::Hip | Self::Sycl => {
+                matches!(platform, Platform::LinuxX64 | Platform::WindowsX64)
+            }
+            Self::Vulkan | Self::OpenCl => !platform.is_macos(),
+            Self::Cpu => true,
+            Self::Metal => platform.is_macos(),
+        }
+    }
+
+    fn flag(self) -> Set {
+        match self {
+            Self::Cuda => Set::CUDA,
+            Self::Hip => Set::HIP,
+            Self::Vulkan => Set::VULKAN,
+            Self::Sycl => Set::SYCL,
+            Self::OpenCl => Set::OPENCL,
+            Self::Cpu => Set::CPU,
+            Self::Metal => Set::METAL,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 bitflags! {
@@ -18,38 +104,64 @@ bitflags! {
         const CUDA = 1;
         /// The AMD HIP backend.
         const HIP = 1 << 1;
+        /// The Vulkan backend.
+        const VULKAN = 1 << 2;
+        /// The Intel SYCL backend.
+        const SYCL = 1 << 3;
+        /// The OpenCL backend.
+        const OPENCL = 1 << 4;
+        /// The CPU backend.
+        const CPU = 1 << 5;
+        /// The Apple Metal backend.
+        const METAL = 1 << 6;
     }
 }
 
 impl Set {
+    /// Probes the running system and returns the [`Set`] of backends that can
+    /// actually run in it.
+    ///
+    /// Use a [`Probe`] to inspect a different filesystem tree.
+    pub fn detect() -> Self {
+        Platform::current()
+            .map(|platform| Probe::new().detect(platform))
+            .unwrap_or_else(Self::empty)
+    }
+
     /// Returns the backends in the [`Set`] that are also available in the current
     /// platform.
     pub fn available(self) -> impl Iterator<Item = Backend> {
-        let mut backends = Vec::new();
+        Platform::current()
+            .into_iter()
+            .flat_map(move |platform| self.available_on(platform))
+    }
 
-        if cfg!(target_os = "macos") {
-            return backends.into_iter();
-        }
+    /// Returns the backends in the [`Set`] that are also available in the given
+    /// [`Platform`].
+    pub fn available_on(self, platform: Platform) -> impl Iterator<Item = Backend> {
+        Backend::ALL.iter().copied().filter(move |backend| {
+            self.contains(backend.flag()) && backend.is_available_on(platform)
+        })
+    }
 
-        if self.contains(Self::CUDA) {
-            backends.push(Backend::Cuda);
-        }
+    /// Returns a new [`Set`] with any unavailable backends filtered out.
+    pub fn normalize(self) -> Self {
+        Self::from_backends(self.available())
+    }
 
-        if self.contains(Self::HIP) {
-            backends.push(Backend::Hip);
-        }
+    /// Returns a new [`Set`] with any backends unavailable in the given [`Platform`]
+    /// filtered out.
+    pub fn normalize_on(self, platform: Platform) -> Self {
+        Self::from_backends(self.available_on(platform))
+    }
 
-        backends.into_iter()
+    fn from_backends(backends: impl Iterator<Item = Backend>) -> Self {
+        backends.fold(Self::empty(), |backends, backend| backends | backend.flag())
     }
+}
 
-    /// Returns a new [`Set`] with any unavailable backends filtered out.
-    pub fn normalize(self) -> Self {
-        self.available().fold(Self::empty(), |backends, backend| {
-            backends
-                | match backend {
-                    Backend::Cuda => Self::CUDA,
-                    Backend::Hip => Self::HIP,
-                }
-        })
+impl From<Backend> for Set {
+    fn from(backend: Backend) -> Self {
+        backend.flag()
     }
 }
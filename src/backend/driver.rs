@@ -0,0 +1,54 @@
+use crate::Platform;
+
+use std::fmt;
+
+/// The version of an installed NVIDIA driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Driver {
+    /// The major version of the [`Driver`] (e.g. `550`).
+    pub major: u32,
+    /// The minor version of the [`Driver`] (e.g. `54`).
+    pub minor: u32,
+}
+
+impl Driver {
+    /// Parses the first driver version found in the given text (e.g. the contents of
+    /// `/proc/driver/nvidia/version` or the output of `nvidia-smi`), if any.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split_whitespace().find_map(|word| {
+            let mut parts = word.split('.');
+
+            let major = parts.next()?.parse().ok()?;
+            let minor = parts.next()?.parse().ok()?;
+
+            Some(Self { major, minor })
+        })
+    }
+
+    /// Returns whether the [`Driver`] can run the given major version of the CUDA
+    /// runtime in the given [`Platform`].
+    pub fn supports(self, cuda: u32, platform: Platform) -> bool {
+        // The minimum driver versions of each CUDA runtime, as documented in
+        // the CUDA toolkit release notes
+        let (linux, windows) = match cuda {
+            11 => ((450, 80), (452, 39)),
+            12 => ((525, 60), (527, 41)),
+            13 => ((580, 65), (580, 88)),
+            _ => return false,
+        };
+
+        let (major, minor) = if platform.is_windows() {
+            windows
+        } else {
+            linux
+        };
+
+        self >= Self { major, minor }
+    }
+}
+
+impl fmt::Display for Driver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
@@ -0,0 +1,297 @@
+use crate::Platform;
+use crate::backend::{Driver, Set};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A probe of the compute devices and drivers installed in a system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    root: PathBuf,
+    is_system: bool,
+}
+
+impl Probe {
+    /// Creates a [`Probe`] of the running system.
+    pub fn new() -> Self {
+        let root = if cfg!(target_os = "windows") {
+            env::var("SystemDrive")
+                .map(|drive| format!("{drive}\\"))
+                .unwrap_or_else(|_| "C:\\".to_owned())
+        } else {
+            "/".to_owned()
+        };
+
+        Self {
+            is_system: true,
+            ..Self::with_root(root)
+        }
+    }
+
+    /// Creates a [`Probe`] that inspects the filesystem tree at the given root
+    /// instead of the running system (e.g. a fake `sys` and `dev` tree).
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            is_system: false,
+        }
+    }
+
+    /// Returns the backends that can actually run in the given [`Platform`].
+    pub fn detect(&self, platform: Platform) -> Set {
+        let mut backends = Set::CPU | Set::METAL;
+
+        if platform.is_windows() {
+            let system = self.windows().join("System32");
+
+            let has_library = |prefix: &str| {
+                has_file(&system, |name| {
+                    name.to_ascii_lowercase().starts_with(prefix)
+                        && name.to_ascii_lowercase().ends_with(".dll")
+                })
+            };
+
+            backends.set(Set::CUDA, has_library("nvcuda"));
+            backends.set(Set::HIP, has_library("amdhip64"));
+            backends.set(Set::VULKAN, has_library("vulkan-1"));
+            backends.set(Set::SYCL, has_library("ze_loader"));
+            backends.set(Set::OPENCL, has_library("opencl"));
+        } else if !platform.is_macos() {
+            let gpus = self.gpus();
+            let has_gpu = |vendor| gpus.contains(&vendor);
+            let has_render_node = has_file(&self.root.join("dev/dri"), |name| {
+                name.starts_with("renderD")
+            });
+
+            let has_nvidia_device = has_file(&self.root.join("dev"), |name| {
+                name.starts_with("nvidia") && name != "nvidia-modeset"
+            }) || has_gpu(NVIDIA)
+                || self.root.join("dev/dxg").exists();
+
+            backends.set(
+                Set::CUDA,
+                has_nvidia_device && self.has_library("libcuda.so"),
+            );
+            backends.set(
+                Set::HIP,
+                self.root.join("dev/kfd").exists()
+                    && (has_gpu(AMD) || self.has_library("libamdhip64.so")),
+            );
+            backends.set(
+                Set::VULKAN,
+                (has_render_node || !gpus.is_empty()) && self.has_library("libvulkan.so"),
+            );
+            backends.set(Set::SYCL, has_gpu(INTEL) && has_render_node);
+            backends.set(Set::OPENCL, self.has_library("libOpenCL.so"));
+        }
+
+        backends.normalize_on(platform)
+    }
+
+    /// Returns the version of the installed NVIDIA [`Driver`], if any.
+    ///
+    /// It reads `/proc/driver/nvidia/version`, falling back to `nvidia-smi` when
+    /// probing the running system.
+    pub fn nvidia_driver(&self) -> Option<Driver> {
+        if let Ok(version) = fs::read_to_string(self.root.join("proc/driver/nvidia/version"))
+            && let Some(driver) = Driver::parse(&version)
+        {
+            return Some(driver);
+        }
+
+        if !self.is_system {
+            return None;
+        }
+
+        let output = process::Command::new("nvidia-smi")
+            .args(["--query-gpu=driver_version", "--format=csv,noheader"])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Driver::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Returns the Windows directory of the system.
+    ///
+    /// It reads `%SystemRoot%` when probing the running system.
+    fn windows(&self) -> PathBuf {
+        self.is_system
+            .then(|| env::var_os("SystemRoot"))
+            .flatten()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.root.join("Windows"))
+    }
+
+    /// Returns the PCI vendor IDs of the display controllers of the system.
+    fn gpus(&self) -> Vec<u16> {
+        let Ok(devices) = fs::read_dir(self.root.join("sys/bus/pci/devices")) else {
+            return Vec::new();
+        };
+
+        devices
+            .filter_map(Result::ok)
+            .filter_map(|device| {
+                let path = device.path();
+
+                let class = read_hex(&path.join("class"))?;
+                let vendor = read_hex(&path.join("vendor"))?;
+
+                (class >> 16 == DISPLAY_CONTROLLER).then_some(vendor as u16)
+            })
+            .collect()
+    }
+
+    fn has_library(&self, name: &str) -> bool {
+        LIBRARY_DIRECTORIES
+            .iter()
+            .any(|directory| has_file(&self.root.join(directory), |file| file.starts_with(name)))
+    }
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The PCI class of display controllers.
+const DISPLAY_CONTROLLER: u32 = 0x03;
+
+const NVIDIA: u16 = 0x10de;
+const AMD: u16 = 0x1002;
+const INTEL: u16 = 0x8086;
+
+/// The directories searched for driver libraries, relative to the root.
+const LIBRARY_DIRECTORIES: &[&str] = &[
+    "lib",
+    "lib64",
+    "usr/lib",
+    "usr/lib64",
+    "usr/lib/x86_64-linux-gnu",
+    "usr/lib/aarch64-linux-gnu",
+    "usr/lib/wsl/lib",
+    "opt/rocm/lib",
+];
+
+fn has_file(directory: &Path, predicate: impl Fn(&str) -> bool) -> bool {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return false;
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .any(|entry| predicate(&entry.file_name().to_string_lossy()))
+}
+
+fn read_hex(path: &Path) -> Option<u32> {
+    let contents = fs::read_to_string(path).ok()?;
+
+    u32::from_str_radix(contents.trim().trim_start_matches("0x"), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "llama-server-probe-{name}-{pid}",
+            pid = std::process::id()
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("create fake root");
+
+        root
+    }
+
+    fn touch(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+
+        fs::create_dir_all(path.parent().expect("parent directory")).expect("create directory");
+        fs::write(path, contents).expect("write file");
+    }
+
+    #[test]
+    fn it_detects_nothing_but_cpu_in_an_empty_system() {
+        let root = fake_root("empty");
+
+        assert_eq!(Probe::with_root(&root).detect(Platform::LinuxX64), Set::CPU);
+
+        fs::remove_dir_all(root).expect("remove fake root");
+    }
+
+    #[test]
+    fn it_detects_gpu_backends_from_devices_and_drivers() {
+        let root = fake_root("gpus");
+
+        touch(
+            &root,
+            "sys/bus/pci/devices/0000:01:00.0/class",
+            "0x030000\n",
+        );
+        touch(&root, "sys/bus/pci/devices/0000:01:00.0/vendor", "0x10de\n");
+        touch(
+            &root,
+            "sys/bus/pci/devices/0000:02:00.0/class",
+            "0x038000\n",
+        );
+        touch(&root, "sys/bus/pci/devices/0000:02:00.0/vendor", "0x1002\n");
+        touch(
+            &root,
+            "sys/bus/pci/devices/0000:03:00.0/class",
+            "0x020000\n",
+        );
+        touch(&root, "sys/bus/pci/devices/0000:03:00.0/vendor", "0x8086\n");
+        touch(&root, "dev/nvidia0", "");
+        touch(&root, "dev/kfd", "");
+        touch(&root, "usr/lib/x86_64-linux-gnu/libcuda.so.1", "");
+        touch(&root, "usr/lib/x86_64-linux-gnu/libvulkan.so.1", "");
+
+        let probe = Probe::with_root(&root);
+
+        assert_eq!(
+            probe.detect(Platform::LinuxX64),
+            Set::CPU | Set::CUDA | Set::HIP | Set::VULKAN
+        );
+        assert_eq!(probe.detect(Platform::LinuxArm64), Set::CPU | Set::VULKAN);
+        assert_eq!(probe.detect(Platform::MacosArm64), Set::CPU | Set::METAL);
+
+        fs::remove_dir_all(root).expect("remove fake root");
+    }
+
+    #[test]
+    fn it_reads_the_nvidia_driver_version() {
+        let root = fake_root("driver");
+        let probe = Probe::with_root(&root);
+
+        assert_eq!(probe.nvidia_driver(), None);
+
+        touch(
+            &root,
+            "proc/driver/nvidia/version",
+            "NVRM version: NVIDIA UNIX x86_64 Kernel Module  470.256.02  Thu May  2 14:37:44 UTC 2024\n\
+             GCC version:  gcc version 12.2.0 (Debian 12.2.0-14)\n",
+        );
+
+        let driver = probe.nvidia_driver().expect("driver version");
+
+        assert_eq!(
+            driver,
+            Driver {
+                major: 470,
+                minor: 256
+            }
+        );
+        assert!(driver.supports(11, Platform::LinuxX64));
+        assert!(!driver.supports(12, Platform::LinuxX64));
+
+        fs::remove_dir_all(root).expect("remove fake root");
+    }
+}
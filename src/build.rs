@@ -1,40 +1,28 @@
-use crate::Error;
-use crate::http;
+use crate::{Asset, Error, Installer, Source};
 
+use chrono::{DateTime, Utc};
+
+use std::collections::BTreeSet;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
 
-const REPOSITORY: &str = "hecrj/llama-server";
-
 /// A specific build of `llama-server`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Build(u32);
 
 impl Build {
-    /// Fetches the latest available [`Build`].
-    pub async fn latest() -> Result<Self, Error> {
-        use serde::Deserialize;
-
-        #[derive(Deserialize)]
-        struct Release {
-            tag_name: String,
-        }
-
-        let client = http::client();
-
-        let latest_release_url =
-            format!("https://api.github.com/repos/{REPOSITORY}/releases/latest");
-
-        let Release { tag_name } = client
-            .get(latest_release_url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
+    /// Fetches the latest [`Build`] available in the given [`Source`].
+    ///
+    /// Use an [`Installer`] to configure the HTTP client.
+    pub async fn latest(source: &Source) -> Result<Self, Error> {
+        Installer::new(source.clone()).latest().await
+    }
 
-        Ok(tag_name.parse()?)
+    /// Fetches all the [`Release`] builds published in the given [`Source`],
+    /// newest first.
+    pub async fn available(source: &Source) -> Result<Vec<Release>, Error> {
+        Installer::new(source.clone()).available().await
     }
 
     /// Creates a new [`Build`] locked to the given number.
@@ -46,10 +34,6 @@ impl Build {
     pub fn number(self) -> u32 {
         self.0
     }
-
-    pub(crate) fn url(self) -> String {
-        format!("https://github.com/{REPOSITORY}/releases/download/{self}")
-    }
 }
 
 impl FromStr for Build {
@@ -73,3 +57,14 @@ impl fmt::Display for Build {
         write!(f, "b{}", self.0)
     }
 }
+
+/// A published [`Build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// The [`Build`] of the [`Release`].
+    pub build: Build,
+    /// The date when the [`Release`] was published.
+    pub published_at: DateTime<Utc>,
+    /// The assets attached to the [`Release`].
+    pub assets: BTreeSet<Asset>,
+}
@@ -1,29 +1,86 @@
-use crate::{Artifact, Backend, Build, Error, Progress};
-
+use crate::cpu;
+use crate::http;
+use crate::lock::Lock;
+use crate::manifest::{self, Checksum};
+use crate::signature;
+use crate::{
+    Artifact, Asset, Backend, Build, Cancellation, Error, Phase, Platform, Progress, PublicKey,
+};
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use reqwest::StatusCode;
 use sipper::{Sipper, Straw, sipper};
 use tokio::fs;
-use tokio::io;
-use tokio::task;
+use zip::result::ZipError;
 
 use std::collections::BTreeSet;
 use std::env;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Cache {
     path: PathBuf,
     build: Build,
+    name: String,
+    executable: &'static str,
 }
 
 impl Cache {
     pub fn new(build: Build) -> Self {
+        let name = build.to_string();
+
         Self {
-            path: root().join(build.to_string()),
+            path: root().join(&name),
             build,
+            name,
+            executable: if cfg!(target_os = "windows") {
+                "llama-server.exe"
+            } else {
+                "llama-server"
+            },
+        }
+    }
+
+    pub fn with_platform(build: Build, platform: Platform) -> Self {
+        if Platform::current() == Some(platform) {
+            return Self::new(build);
+        }
+
+        // Builds for foreign platforms are kept apart, so they are only listed
+        // for their platform
+        let name = format!("{build}-{platform}");
+
+        Self {
+            path: root().join(&name),
+            build,
+            name,
+            executable: platform.executable(),
         }
     }
 
     pub async fn list() -> Result<Vec<Self>, Error> {
+        Self::list_by(|name| Some(Self::new(name.parse().ok()?))).await
+    }
+
+    pub async fn list_with_platform(platform: Platform) -> Result<Vec<Self>, Error> {
+        if Platform::current() == Some(platform) {
+            return Self::list().await;
+        }
+
+        let suffix = format!("-{platform}");
+
+        Self::list_by(|name| {
+            let build = name.strip_suffix(&suffix)?.parse().ok()?;
+
+            Some(Self::with_platform(build, platform))
+        })
+        .await
+    }
+
+    async fn list_by(parse: impl Fn(&str) -> Option<Self>) -> Result<Vec<Self>, Error> {
         fs::create_dir_all(root()).await?;
 
         let mut caches = Vec::new();
@@ -40,11 +97,11 @@ impl Cache {
                 continue;
             };
 
-            let Ok(build) = name.to_string_lossy().parse() else {
+            let Some(cache) = parse(&name.to_string_lossy()) else {
                 continue;
             };
 
-            caches.push(Cache::new(build));
+            caches.push(cache);
         }
 
         Ok(caches)
@@ -54,91 +111,405 @@ impl Cache {
         self.build
     }
 
-    pub fn download(&self, artifact: Artifact) -> impl Straw<Component, Progress, Error> {
-        sipper(async move |sender| {
-            fs::create_dir_all(&self.path).await?;
+    pub async fn lock(&self) -> Result<Lock, Error> {
+        Lock::acquire(self.lock_path(None)).await
+    }
 
-            let component = match artifact {
-                Artifact::Server => Component::Server,
-                Artifact::Backend(backend) => Component::Backend(backend),
-            };
+    pub fn try_lock(&self) -> Result<Option<Lock>, Error> {
+        Lock::try_acquire(&self.lock_path(None))
+    }
+
+    pub async fn clean(&self) -> Result<(), Error> {
+        if !fs::try_exists(&self.path).await? {
+            return Ok(());
+        }
+
+        let mut read_dir = fs::read_dir(&self.path).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
 
-            if !fs::try_exists(self.path.join(component.directory())).await? {
-                let file = fs::File::create(self.path.join(component.archive())).await?;
+            if !entry.file_type().await?.is_dir() {
+                fs::remove_file(path).await?;
+            } else if entry.file_name() != DOWNLOADS && !is_installed(&path).await? {
+                fs::remove_dir_all(path).await?;
+            }
+        }
+
+        Ok(())
+    }
 
-                artifact
-                    .download(self.build, &mut io::BufWriter::new(file))
-                    .run(sender)
-                    .await?;
+    pub async fn discard(&self) -> Result<(), Error> {
+        self.clean().await?;
 
-                task::spawn_blocking({
-                    let cache = self.clone();
+        let downloads = self.path.join(DOWNLOADS);
+
+        if fs::try_exists(&downloads).await? {
+            fs::remove_dir_all(downloads).await?;
+        }
 
-                    move || cache.extract(component)
+        if fs::try_exists(&self.path).await?
+            && fs::read_dir(&self.path)
+                .await?
+                .next_entry()
+                .await?
+                .is_none()
+        {
+            fs::remove_dir(&self.path).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn download(
+        &self,
+        http: &http::Client,
+        entry: &manifest::Entry,
+        public_keys: &[PublicKey],
+        cancellation: &Cancellation,
+    ) -> impl Straw<Component, Phase, Error> {
+        sipper(async move |mut sender| {
+            let component = Component::new(&entry.asset);
+
+            if self.is_installed(component).await? {
+                return Ok(component);
+            }
+
+            fs::create_dir_all(self.staging()).await?;
+            fs::create_dir_all(self.path.join(DOWNLOADS)).await?;
+
+            let archive = self.path.join(DOWNLOADS).join(component.archive());
+
+            let mut last = Progress::default();
+
+            let download = entry
+                .download(http, &archive)
+                .with(|progress| {
+                    last = progress;
+                    Phase::Downloading(progress)
                 })
-                .await??;
+                .run(&sender)
+                .await;
+
+            match download {
+                Err(Error::RequestFailed(error))
+                    if error.status() == Some(StatusCode::NOT_FOUND) =>
+                {
+                    return Err(Error::ArtifactNotFound {
+                        artifact: entry.asset.artifact,
+                        build: self.build,
+                    });
+                }
+                // The partial archive is kept, so a later install can resume it
+                Err(Error::IOFailed(error)) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(Error::TruncatedDownload {
+                        artifact: entry.asset.artifact,
+                        expected: last.total,
+                        received: last.downloaded,
+                    });
+                }
+                download => download?,
+            }
 
-                fs::remove_file(self.path.join(component.archive())).await?;
+            let received = fs::metadata(&archive).await?.len();
+
+            if received < entry.size {
+                fs::remove_file(&archive).await?;
+
+                return Err(Error::TruncatedDownload {
+                    artifact: entry.asset.artifact,
+                    expected: entry.size,
+                    received,
+                });
+            }
+
+            if entry.sha256.is_some() || !public_keys.is_empty() {
+                sender.send(Phase::Verifying).await;
             }
 
+            if let Some(expected) = entry.sha256 {
+                let actual = cancellation
+                    .spawn_blocking({
+                        let archive = archive.clone();
+                        let cancellation = cancellation.clone();
+
+                        move || checksum(&archive, &cancellation)
+                    })
+                    .await??;
+
+                if actual != expected {
+                    fs::remove_file(&archive).await?;
+
+                    return Err(Error::ChecksumMismatch {
+                        artifact: entry.asset.artifact,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+
+            if !public_keys.is_empty() {
+                let Some(signature) = entry.fetch_signature(http).await? else {
+                    fs::remove_file(&archive).await?;
+
+                    return Err(Error::Unsigned {
+                        artifact: entry.asset.artifact,
+                    });
+                };
+
+                let is_valid = cancellation
+                    .spawn_blocking({
+                        let archive = archive.clone();
+                        let public_keys = public_keys.to_vec();
+
+                        move || signature::verify(&archive, &signature, &public_keys)
+                    })
+                    .await??;
+
+                if !is_valid {
+                    fs::remove_file(&archive).await?;
+
+                    return Err(Error::InvalidSignature {
+                        artifact: entry.asset.artifact,
+                    });
+                }
+            }
+
+            let directory = self.staging().join(component.directory());
+
+            if fs::try_exists(&directory).await? {
+                fs::remove_dir_all(&directory).await?;
+            }
+
+            let (files, mut extracted) = mpsc::unbounded();
+
+            let extraction = cancellation.spawn_blocking({
+                let archive = archive.clone();
+                let directory = directory.clone();
+                let cancellation = cancellation.clone();
+
+                let artifact = entry.asset.artifact;
+
+                move || {
+                    extract(
+                        artifact,
+                        &archive,
+                        &directory,
+                        &cancellation,
+                        |extracted, total| {
+                            let _ = files.unbounded_send(Phase::Extracting { extracted, total });
+                        },
+                    )
+                }
+            });
+
+            while let Some(phase) = extracted.next().await {
+                sender.send(phase).await;
+            }
+
+            if let Err(error) = extraction.await? {
+                if let Error::ArchiveCorrupted { .. } = error {
+                    fs::remove_file(&archive).await?;
+                }
+
+                return Err(error);
+            }
+
+            fs::remove_file(&archive).await?;
+
+            self.finalize(&component.directory()).await?;
+
             Ok(component)
         })
     }
 
-    pub async fn link(
+    pub async fn is_installed(&self, component: Component) -> Result<bool, Error> {
+        is_installed(&self.path.join(component.directory())).await
+    }
+
+    pub fn link(
         &self,
         components: impl IntoIterator<Item = Component>,
-    ) -> Result<PathBuf, Error> {
+    ) -> impl Straw<PathBuf, (Component, Phase), Error> {
         let instance = Instance::new(components);
-        let path = self.path.join(instance.directory());
 
-        if !fs::try_exists(&path).await? {
-            fs::create_dir(&path).await?;
+        sipper(async move |mut sender| {
+            let directory = instance.directory();
+            let path = self.path.join(&directory);
+
+            let _lock = Lock::acquire(self.lock_path(Some(&directory))).await?;
 
-            for component in instance.components {
-                let mut read_component =
-                    fs::read_dir(self.path.join(component.directory())).await?;
+            if !is_installed(&path).await? {
+                let staged = self.staging().join(&directory);
 
-                while let Some(entry) = read_component.next_entry().await? {
-                    if !entry.file_type().await?.is_file() {
-                        continue;
+                if fs::try_exists(&staged).await? {
+                    fs::remove_dir_all(&staged).await?;
+                }
+
+                fs::create_dir_all(&staged).await?;
+
+                for component in instance.components {
+                    let mut files = Vec::new();
+                    let mut read_component =
+                        fs::read_dir(self.path.join(component.directory())).await?;
+
+                    while let Some(entry) = read_component.next_entry().await? {
+                        if entry.file_type().await?.is_file() && entry.file_name() != MARKER {
+                            files.push(entry.path());
+                        }
                     }
 
-                    let entry_path = entry.path();
+                    let total = files.len();
+
+                    for (linked, file) in files.into_iter().enumerate() {
+                        sender
+                            .send((component, Phase::Linking { linked, total }))
+                            .await;
 
-                    let Some(file_name) = entry_path.file_name() else {
-                        continue;
-                    };
+                        let Some(file_name) = file.file_name() else {
+                            continue;
+                        };
 
-                    let dest_path = path.join(file_name);
+                        let dest_path = staged.join(file_name);
 
-                    if fs::try_exists(&dest_path).await? {
-                        continue;
+                        if fs::try_exists(&dest_path).await? {
+                            continue;
+                        }
+
+                        fs::hard_link(&file, dest_path).await?;
                     }
 
-                    fs::hard_link(entry_path, dest_path).await?;
+                    sender
+                        .send((
+                            component,
+                            Phase::Linking {
+                                linked: total,
+                                total,
+                            },
+                        ))
+                        .await;
                 }
+
+                self.finalize(&directory).await?;
+            }
+
+            Ok(path.join(self.executable))
+        })
+    }
+
+    pub async fn remove_backend(
+        &self,
+        executable: &Path,
+        backend: Backend,
+    ) -> Result<PathBuf, Error> {
+        let _lock = self.lock().await?;
+
+        let instance = self
+            .instance(executable)
+            .ok_or_else(|| Error::InstanceNotFound {
+                build: self.build,
+                executable: executable.to_path_buf(),
+            })?;
+
+        let artifact = Artifact::Backend(backend);
+
+        let executable = self
+            .link(
+                instance
+                    .components
+                    .into_iter()
+                    .filter(|component| component.artifact() != artifact),
+            )
+            .await?;
+
+        let mut read_dir = fs::read_dir(&self.path).await?;
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            let is_stale = match name.strip_prefix("backend-") {
+                Some(component) => Component::parse(component)
+                    .is_some_and(|component| component.artifact() == artifact),
+                None => Instance::parse(&name).is_some_and(|instance| {
+                    instance
+                        .components
+                        .iter()
+                        .any(|component| component.artifact() == artifact)
+                }),
+            };
+
+            if is_stale {
+                fs::remove_dir_all(entry.path()).await?;
             }
         }
 
-        Ok(path.join(if cfg!(target_os = "windows") {
-            "llama-server.exe"
-        } else {
-            "llama-server"
-        }))
+        Ok(executable)
+    }
+
+    pub async fn unlink(&self, executable: &Path) -> Result<(), Error> {
+        let _lock = self.lock().await?;
+
+        // Instances without backends live in the directory of the server component
+        if let Some(instance) = self.instance(executable)
+            && instance.components.len() > 1
+            && let Some(directory) = executable.parent()
+            && fs::try_exists(directory).await?
+        {
+            fs::remove_dir_all(directory).await?;
+        }
+
+        Ok(())
     }
 
     pub async fn delete(self) -> Result<(), Error> {
+        let _lock = self.lock().await?;
+
         fs::remove_dir_all(self.path).await?;
         Ok(())
     }
 
-    fn extract(&self, component: Component) -> Result<(), Error> {
-        let directory = self.path.join(component.directory());
-        let file = std::fs::File::open(self.path.join(component.archive()))?;
+    fn lock_path(&self, instance: Option<&str>) -> PathBuf {
+        let locks = root().join(".locks");
+
+        match instance {
+            Some(instance) => locks.join(format!("{}-{instance}.lock", self.name)),
+            None => locks.join(format!("{}.lock", self.name)),
+        }
+    }
+
+    fn instance(&self, executable: &Path) -> Option<Instance> {
+        let directory = executable.parent()?;
+
+        if directory.parent()? != self.path {
+            return None;
+        }
+
+        Instance::parse(&directory.file_name()?.to_string_lossy())
+    }
+
+    fn staging(&self) -> PathBuf {
+        self.path.join(STAGING)
+    }
 
-        let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file))?;
-        archive.extract(directory)?;
+    async fn finalize(&self, directory: &str) -> Result<(), Error> {
+        let staged = self.staging().join(directory);
+        let path = self.path.join(directory);
+
+        fs::File::create(staged.join(MARKER))
+            .await?
+            .sync_all()
+            .await?;
+
+        if fs::try_exists(&path).await? {
+            fs::remove_dir_all(&path).await?;
+        }
+
+        fs::rename(staged, path).await?;
 
         Ok(())
     }
@@ -146,24 +517,65 @@ impl Cache {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Component {
-    Server,
+    Server(cpu::Variant),
     Backend(Backend),
+    Cuda(u32),
 }
 
 impl Component {
-    fn directory(self) -> &'static str {
+    pub fn new(asset: &Asset) -> Self {
+        match asset.artifact {
+            Artifact::Server => Self::Server(asset.cpu),
+            Artifact::Backend(Backend::Cuda) if let Some(major) = asset.cuda => Self::Cuda(major),
+            Artifact::Backend(backend) => Self::Backend(backend),
+        }
+    }
+
+    pub fn artifact(self) -> Artifact {
         match self {
-            Self::Server => "server",
+            Self::Server(_) => Artifact::Server,
+            Self::Backend(backend) => Artifact::Backend(backend),
+            Self::Cuda(_) => Artifact::Backend(Backend::Cuda),
+        }
+    }
+
+    fn directory(self) -> String {
+        match self {
+            Self::Server(cpu) => match cpu {
+                cpu::Variant::Portable => "server",
+                cpu::Variant::Avx2 => "server-avx2",
+                cpu::Variant::Avx512 => "server-avx512",
+            }
+            .to_owned(),
             Self::Backend(backend) => match backend {
                 Backend::Cuda => "backend-cuda",
                 Backend::Hip => "backend-hip",
-            },
+                Backend::Vulkan => "backend-vulkan",
+                Backend::Sycl => "backend-sycl",
+                Backend::OpenCl => "backend-opencl",
+                Backend::Cpu => "backend-cpu",
+                Backend::Metal => "backend-metal",
+            }
+            .to_owned(),
+            Self::Cuda(major) => format!("backend-cuda{major}"),
         }
     }
 
     fn archive(self) -> String {
         format!("{}.zip", self.directory())
     }
+
+    fn parse(name: &str) -> Option<Self> {
+        if let Some(backend) = Backend::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == name)
+        {
+            return Some(Self::Backend(backend));
+        }
+
+        name.strip_prefix("cuda")?.parse().ok().map(Self::Cuda)
+    }
 }
 
 struct Instance {
@@ -173,20 +585,221 @@ struct Instance {
 impl Instance {
     fn new(components: impl IntoIterator<Item = Component>) -> Self {
         let mut components = BTreeSet::from_iter(components);
-        let _ = components.insert(Component::Server);
+
+        if !components
+            .iter()
+            .any(|component| matches!(component, Component::Server(_)))
+        {
+            let _ = components.insert(Component::Server(cpu::Variant::Portable));
+        }
 
         Self { components }
     }
 
+    fn parse(directory: &str) -> Option<Self> {
+        let mut names = directory.split('-');
+
+        if names.next()? != "server" {
+            return None;
+        }
+
+        let mut cpu = cpu::Variant::Portable;
+        let mut components = BTreeSet::new();
+
+        for name in names {
+            if let Some(variant) = cpu::Variant::ALL
+                .iter()
+                .copied()
+                .find(|variant| *variant != cpu::Variant::Portable && variant.name() == name)
+            {
+                cpu = variant;
+            } else {
+                let _ = components.insert(Component::parse(name)?);
+            }
+        }
+
+        let _ = components.insert(Component::Server(cpu));
+
+        Some(Self { components })
+    }
+
     fn directory(&self) -> String {
         self.components
             .iter()
-            .map(|component| component.directory().trim_start_matches("backend-"))
+            .map(|component| {
+                component
+                    .directory()
+                    .trim_start_matches("backend-")
+                    .to_owned()
+            })
             .collect::<Vec<_>>()
             .join("-")
     }
 }
 
+/// The directory where installs are prepared before being moved into place.
+const STAGING: &str = ".staging";
+
+/// The directory where partial downloads are kept, so they can be resumed.
+const DOWNLOADS: &str = ".downloads";
+
+/// The file marking a directory as completely installed.
+const MARKER: &str = ".installed";
+
+async fn is_installed(path: &Path) -> Result<bool, Error> {
+    Ok(fs::try_exists(path.join(MARKER)).await?)
+}
+
+fn checksum(archive: &Path, cancellation: &Cancellation) -> Result<Checksum, Error> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(archive)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+
+    loop {
+        if cancellation.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let read = io::Read::read(&mut file, &mut buffer)?;
+
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(Checksum::from_bytes(hasher.finalize().into()))
+}
+
+fn extract(
+    artifact: Artifact,
+    archive: &Path,
+    directory: &Path,
+    cancellation: &Cancellation,
+    mut on_file: impl FnMut(usize, usize),
+) -> Result<(), Error> {
+    let corrupted = |error| Error::ArchiveCorrupted {
+        artifact,
+        path: archive.to_path_buf(),
+        error: Arc::new(error),
+    };
+
+    let file = std::fs::File::open(archive)?;
+
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(file)).map_err(corrupted)?;
+    let total = archive.len();
+
+    std::fs::create_dir_all(directory)?;
+    on_file(0, total);
+
+    for index in 0..total {
+        if cancellation.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let mut file = archive.by_index(index).map_err(corrupted)?;
+
+        let Some(name) = file.enclosed_name() else {
+            return Err(corrupted(ZipError::InvalidArchive(
+                format!("invalid path: {}", file.name()).into(),
+            )));
+        };
+
+        // A symbolic link extracted earlier could redirect writes outside of the directory
+        if has_symlink(directory, &name) {
+            return Err(corrupted(ZipError::InvalidArchive(
+                format!("path through symbolic link: {}", file.name()).into(),
+            )));
+        }
+
+        let path = directory.join(&name);
+
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            #[cfg(unix)]
+            if file.is_symlink() {
+                let mut target = String::new();
+                let _ = io::Read::read_to_string(&mut file, &mut target)?;
+
+                if !is_enclosed(&name, Path::new(&target)) {
+                    return Err(corrupted(ZipError::InvalidArchive(
+                        format!(
+                            "symbolic link outside of archive: {} -> {target}",
+                            file.name()
+                        )
+                        .into(),
+                    )));
+                }
+
+                std::os::unix::fs::symlink(target, &path)?;
+                on_file(index + 1, total);
+
+                continue;
+            }
+
+            let mut output = std::fs::File::create(&path)?;
+            let _ = io::copy(&mut file, &mut output)?;
+
+            #[cfg(unix)]
+            if let Some(mode) = file.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+
+        on_file(index + 1, total);
+    }
+
+    Ok(())
+}
+
+/// Returns whether any component of the given path inside the directory is a
+/// symbolic link.
+fn has_symlink(directory: &Path, name: &Path) -> bool {
+    let mut path = directory.to_path_buf();
+
+    name.components().any(|component| {
+        path.push(component);
+
+        path.symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+    })
+}
+
+/// Returns whether the target of a symbolic link at the given relative path
+/// resolves inside of its root directory.
+#[cfg(unix)]
+fn is_enclosed(link: &Path, target: &Path) -> bool {
+    use std::path::Component;
+
+    let mut depth = link.components().count().saturating_sub(1);
+
+    target.components().all(|component| match component {
+        Component::Normal(_) => {
+            depth += 1;
+            true
+        }
+        Component::CurDir => true,
+        Component::ParentDir => match depth.checked_sub(1) {
+            Some(parent) => {
+                depth = parent;
+                true
+            }
+            None => false,
+        },
+        Component::RootDir | Component::Prefix(_) => false,
+    })
+}
+
 fn root() -> PathBuf {
     env::var("LLAMA_SERVER_CACHE_DIR")
         .map(PathBuf::from)
@@ -197,3 +810,135 @@ fn root() -> PathBuf {
                 .to_path_buf()
         })
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn archive(name: &str, build: impl FnOnce(&mut zip::ZipWriter<std::fs::File>)) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "llama-server-extract-{name}-{pid}",
+            pid = std::process::id()
+        ));
+
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).expect("create directory");
+
+        let path = directory.join("archive.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).expect("create archive"));
+
+        build(&mut writer);
+
+        let _ = writer.finish().expect("finish archive");
+
+        path
+    }
+
+    #[test]
+    fn it_extracts_symlinks_inside_the_directory() {
+        let archive = archive("inside", |writer| {
+            let options = SimpleFileOptions::default();
+
+            writer
+                .start_file("lib/libggml.so.1", options)
+                .expect("start file");
+            writer.write_all(b"ggml").expect("write file");
+            writer
+                .add_symlink("lib/libggml.so", "libggml.so.1", options)
+                .expect("add symlink");
+            writer
+                .add_symlink("libggml.so", "lib/../lib/libggml.so.1", options)
+                .expect("add symlink");
+        });
+
+        let directory = archive.with_file_name("output");
+
+        extract(
+            Artifact::Server,
+            &archive,
+            &directory,
+            &Cancellation::new(),
+            |_, _| {},
+        )
+        .expect("extract archive");
+
+        assert_eq!(
+            std::fs::read(directory.join("libggml.so")).expect("read symlink"),
+            b"ggml"
+        );
+
+        std::fs::remove_dir_all(archive.parent().expect("parent")).expect("remove directory");
+    }
+
+    #[test]
+    fn it_rejects_symlinks_escaping_the_directory() {
+        let outside = std::env::temp_dir().join(format!(
+            "llama-server-extract-outside-{pid}",
+            pid = std::process::id()
+        ));
+
+        for (name, target) in [
+            ("absolute", outside.to_string_lossy().into_owned()),
+            ("relative", "../../outside".to_owned()),
+        ] {
+            let archive = archive(name, |writer| {
+                let options = SimpleFileOptions::default();
+
+                writer
+                    .add_symlink("evil", &target, options)
+                    .expect("add symlink");
+                writer
+                    .start_file("evil/pwned", options)
+                    .expect("start file");
+                writer.write_all(b"pwned").expect("write file");
+            });
+
+            let directory = archive.with_file_name("output");
+            let result = extract(
+                Artifact::Server,
+                &archive,
+                &directory,
+                &Cancellation::new(),
+                |_, _| {},
+            );
+
+            assert!(matches!(result, Err(Error::ArchiveCorrupted { .. })));
+            assert!(!outside.join("pwned").exists());
+
+            std::fs::remove_dir_all(archive.parent().expect("parent")).expect("remove directory");
+        }
+    }
+
+    #[test]
+    fn it_never_writes_through_symlinks() {
+        let archive = archive("through", |writer| {
+            let options = SimpleFileOptions::default();
+
+            writer.add_directory("lib", options).expect("add directory");
+            writer
+                .add_symlink("current", "lib", options)
+                .expect("add symlink");
+            writer
+                .start_file("current/libggml.so", options)
+                .expect("start file");
+            writer.write_all(b"ggml").expect("write file");
+        });
+
+        let directory = archive.with_file_name("output");
+        let result = extract(
+            Artifact::Server,
+            &archive,
+            &directory,
+            &Cancellation::new(),
+            |_, _| {},
+        );
+
+        assert!(matches!(result, Err(Error::ArchiveCorrupted { .. })));
+        assert!(!directory.join("lib/libggml.so").exists());
+
+        std::fs::remove_dir_all(archive.parent().expect("parent")).expect("remove directory");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::Error;
+
+use futures::future::{self, Either};
+use tokio::sync::Notify;
+use tokio::task;
+
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+
+/// A handle to cancel in-flight installs.
+///
+/// Cloning a [`Cancellation`] produces a handle to the same cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    is_cancelled: AtomicBool,
+    notify: Notify,
+    tasks: AtomicUsize,
+    settled: Notify,
+}
+
+impl Cancellation {
+    /// Creates a new [`Cancellation`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels any install using the [`Cancellation`].
+    pub fn cancel(&self) {
+        self.state
+            .is_cancelled
+            .store(true, atomic::Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    /// Returns whether the [`Cancellation`] has been triggered.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.is_cancelled.load(atomic::Ordering::SeqCst)
+    }
+
+    /// Runs the given future until it finishes or the [`Cancellation`] is triggered,
+    /// in which case it fails with [`Error::Cancelled`].
+    pub(crate) async fn guard<T>(
+        &self,
+        future: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let mut notified = pin!(self.state.notify.notified());
+        let _ = notified.as_mut().enable();
+
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        match future::select(notified, pin!(future)).await {
+            Either::Left(((), _)) => Err(Error::Cancelled),
+            Either::Right((result, _)) => result,
+        }
+    }
+
+    /// Runs the given blocking function in a separate thread, keeping track of it
+    /// until it finishes.
+    ///
+    /// Dropping the returned handle does not stop the function, so it should check
+    /// [`is_cancelled`](Self::is_cancelled) periodically.
+    pub(crate) fn spawn_blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> task::JoinHandle<T> {
+        let task = Task::new(&self.state);
+
+        task::spawn_blocking(move || {
+            let _task = task;
+
+            f()
+        })
+    }
+
+    /// Waits until every blocking function spawned with the [`Cancellation`] finishes.
+    pub(crate) async fn settle(&self) {
+        loop {
+            let mut settled = pin!(self.state.settled.notified());
+            let _ = settled.as_mut().enable();
+
+            if self.state.tasks.load(atomic::Ordering::SeqCst) == 0 {
+                return;
+            }
+
+            settled.await;
+        }
+    }
+}
+
+/// A blocking function in flight.
+struct Task(Arc<State>);
+
+impl Task {
+    fn new(state: &Arc<State>) -> Self {
+        let _ = state.tasks.fetch_add(1, atomic::Ordering::SeqCst);
+
+        Self(state.clone())
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if self.0.tasks.fetch_sub(1, atomic::Ordering::SeqCst) == 1 {
+            self.0.settled.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Pick the instruction sets of your server binaries.
+use std::fmt;
+
+/// A build of the server binary optimized for a specific set of CPU instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    /// The most portable build, which runs in any CPU of its platform.
+    #[default]
+    Portable,
+    /// A build using AVX2, FMA, and F16C instructions.
+    Avx2,
+    /// A build using AVX-512 instructions.
+    Avx512,
+}
+
+impl Variant {
+    /// All the CPU variants, from the most portable to the fastest.
+    pub const ALL: &[Self] = &[Self::Portable, Self::Avx2, Self::Avx512];
+
+    /// Returns the name of the [`Variant`] (e.g. `avx2`).
+    ///
+    /// Published file names of non-portable variants end with it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Portable => "portable",
+            Self::Avx2 => "avx2",
+            Self::Avx512 => "avx512",
+        }
+    }
+
+    /// Returns whether the CPU of the running system supports the instructions
+    /// of the [`Variant`].
+    pub fn is_supported(self) -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::is_x86_feature_detected;
+
+            match self {
+                Self::Portable => true,
+                Self::Avx2 => {
+                    is_x86_feature_detected!("avx2")
+                        && is_x86_feature_detected!("fma")
+                        && is_x86_feature_detected!("f16c")
+                }
+                Self::Avx512 => {
+                    Self::Avx2.is_supported()
+                        && is_x86_feature_detected!("avx512f")
+                        && is_x86_feature_detected!("avx512bw")
+                        && is_x86_feature_detected!("avx512vl")
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            self == Self::Portable
+        }
+    }
+
+    /// Returns the variants that can run in the running system, fastest first.
+    pub fn detect() -> impl Iterator<Item = Self> {
+        Self::ALL
+            .iter()
+            .rev()
+            .copied()
+            .filter(|variant| variant.is_supported())
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{Backend, Error};
+
+use tokio::process;
+
+use std::path::{Path, PathBuf};
+
+/// A compute device visible to a [`Server`](crate::Server).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    /// The name of the [`Device`] (e.g. `CUDA0`).
+    pub name: String,
+    /// The human-readable description of the [`Device`] (e.g. `NVIDIA GeForce RTX 4090`).
+    pub description: String,
+    /// The [`Backend`] of the [`Device`].
+    pub backend: Backend,
+    /// The total memory of the [`Device`], in bytes.
+    pub total_memory: u64,
+    /// The free memory of the [`Device`], in bytes.
+    pub free_memory: u64,
+}
+
+impl Device {
+    fn parse(line: &str) -> Option<Self> {
+        let (name, rest) = line.trim().split_once(": ")?;
+        let (description, memory) = rest.rsplit_once(" (")?;
+
+        let (total, free) = memory.strip_suffix(" MiB free)")?.split_once(" MiB, ")?;
+
+        Some(Self {
+            backend: backend(name)?,
+            name: name.to_owned(),
+            description: description.to_owned(),
+            total_memory: total.trim().parse::<u64>().ok()? * MIB,
+            free_memory: free.trim().parse::<u64>().ok()? * MIB,
+        })
+    }
+}
+
+/// The compute [`Device`]s visible to a [`Server`](crate::Server).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceList {
+    /// The [`Device`]s found.
+    pub devices: Vec<Device>,
+    /// The backend libraries that failed to load (e.g. because of a missing runtime).
+    pub failures: Vec<LoadFailure>,
+}
+
+/// A backend library of a [`Server`](crate::Server) that failed to load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadFailure {
+    /// The path of the backend library.
+    pub library: PathBuf,
+    /// The reason reported by the loader.
+    pub reason: String,
+}
+
+impl LoadFailure {
+    fn parse(line: &str) -> Option<Self> {
+        let (_, failure) = line.split_once("failed to load ")?;
+        let (library, reason) = failure.split_once(": ").unwrap_or((failure, ""));
+
+        Some(Self {
+            library: library.trim().into(),
+            reason: reason.trim().to_owned(),
+        })
+    }
+
+    fn is_backend(&self, backend: Backend) -> bool {
+        self.library.file_name().is_some_and(|name| {
+            name.to_string_lossy()
+                .contains(&format!("ggml-{}", backend.name()))
+        })
+    }
+}
+
+/// The compute devices an [`Instance`](crate::Instance) runs in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Devices {
+    /// Runs in all the devices visible to the [`Server`](crate::Server).
+    #[default]
+    All,
+    /// Runs only in the CPU.
+    Cpu,
+    /// Runs only in the devices of the given [`Backend`].
+    Backend(Backend),
+    /// Runs only in the [`Device`]s with the given names (e.g. `CUDA0`).
+    Only(Vec<String>),
+}
+
+/// How a model is split across multiple devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SplitMode {
+    /// Uses a single device; the main GPU.
+    Single,
+    /// Splits layers and KV cache across devices.
+    Layer,
+    /// Splits rows across devices.
+    Row,
+}
+
+impl SplitMode {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Single => "none",
+            Self::Layer => "layer",
+            Self::Row => "row",
+        }
+    }
+}
+
+/// Returns the value of the `--device` argument of the given [`Devices`], if any.
+///
+/// The devices are listed with the given environment variables, so that they match
+/// the ones visible to the [`Instance`](crate::Instance).
+pub async fn select(
+    executable: &Path,
+    devices: &Devices,
+    environment: &[(&str, String)],
+) -> Result<Option<String>, Error> {
+    Ok(match devices {
+        Devices::All => None,
+        Devices::Cpu | Devices::Backend(Backend::Cpu) => Some("none".to_owned()),
+        Devices::Backend(backend) => {
+            let DeviceList { devices, failures } = list(executable, environment).await?;
+
+            let names: Vec<_> = devices
+                .into_iter()
+                .filter(|device| device.backend == *backend)
+                .map(|device| device.name)
+                .collect();
+
+            if names.is_empty() {
+                return Err(
+                    match failures
+                        .into_iter()
+                        .find(|failure| failure.is_backend(*backend))
+                    {
+                        Some(LoadFailure { library, reason }) => {
+                            Error::BackendLoadFailed { library, reason }
+                        }
+                        None => Error::DeviceNotFound { backend: *backend },
+                    },
+                );
+            }
+
+            Some(names.join(","))
+        }
+        Devices::Only(names) => Some(names.join(",")),
+    })
+}
+
+pub async fn list(executable: &Path, environment: &[(&str, String)]) -> Result<DeviceList, Error> {
+    let output = process::Command::new(executable)
+        .arg("--list-devices")
+        .envs(environment.iter().cloned())
+        .kill_on_drop(true)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(Error::ProcessExited {
+            status: output.status,
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let devices = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("Available devices:"))
+        .skip(1)
+        .filter_map(Device::parse)
+        .collect();
+
+    // Backend libraries are loaded dynamically, so a missing runtime only
+    // shows up in the logs
+    let failures = stderr
+        .lines()
+        .chain(stdout.lines())
+        .filter_map(LoadFailure::parse)
+        .collect();
+
+    Ok(DeviceList { devices, failures })
+}
+
+const MIB: u64 = 1024 * 1024;
+
+fn backend(name: &str) -> Option<Backend> {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    Some(match prefix {
+        "CUDA" => Backend::Cuda,
+        "ROCm" | "HIP" => Backend::Hip,
+        "Vulkan" => Backend::Vulkan,
+        "SYCL" => Backend::Sycl,
+        "OpenCL" | "GPUOpenCL" => Backend::OpenCl,
+        "CPU" => Backend::Cpu,
+        "Metal" | "MTL" => Backend::Metal,
+        _ => return None,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use crate::{Build, Server, backend, cpu};
+
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn fake_server(name: &str, script: &str) -> Server {
+        let directory = std::env::temp_dir().join(format!(
+            "llama-server-devices-{name}-{pid}",
+            pid = std::process::id()
+        ));
+
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("create directory");
+
+        let path = directory.join("llama-server");
+
+        fs::write(&path, format!("#!/bin/sh\n{script}\n")).expect("write script");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("set permissions");
+
+        Server {
+            build: Build::locked(0),
+            backends: backend::Set::CPU,
+            cpu: cpu::Variant::Portable,
+            executable: path,
+        }
+    }
+
+    fn remove(server: Server) {
+        fs::remove_dir_all(server.executable.parent().expect("server directory"))
+            .expect("remove server directory");
+    }
+
+    #[tokio::test]
+    async fn it_lists_devices() -> Result<(), Error> {
+        let server = fake_server(
+            "list",
+            "echo 'load_backend: loaded CUDA backend from ./libggml-cuda.so' >&2\n\
+             echo 'Available devices:'\n\
+             echo '  CUDA0: NVIDIA GeForce RTX 4090 (24080 MiB, 23664 MiB free)'\n\
+             echo '  Vulkan1: AMD Radeon (RADV NAVI31) (20464 MiB, 20000 MiB free)'",
+        );
+
+        let DeviceList { devices, failures } = server.devices().await?;
+
+        assert!(failures.is_empty());
+        assert_eq!(
+            devices,
+            [
+                Device {
+                    name: "CUDA0".to_owned(),
+                    description: "NVIDIA GeForce RTX 4090".to_owned(),
+                    backend: Backend::Cuda,
+                    total_memory: 24080 * MIB,
+                    free_memory: 23664 * MIB,
+                },
+                Device {
+                    name: "Vulkan1".to_owned(),
+                    description: "AMD Radeon (RADV NAVI31)".to_owned(),
+                    backend: Backend::Vulkan,
+                    total_memory: 20464 * MIB,
+                    free_memory: 20000 * MIB,
+                },
+            ]
+        );
+
+        remove(server);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_selects_devices_visible_to_the_instance() -> Result<(), Error> {
+        let server = fake_server(
+            "visible",
+            "echo 'Available devices:'\n\
+             if [ \"$CUDA_VISIBLE_DEVICES\" != '1' ]; then\n\
+               echo '  CUDA0: NVIDIA GeForce RTX 4090 (24080 MiB, 23664 MiB free)'\n\
+             fi\n\
+             echo '  CUDA1: NVIDIA GeForce RTX 3090 (24080 MiB, 23664 MiB free)'",
+        );
+
+        let devices = Devices::Backend(Backend::Cuda);
+
+        assert_eq!(
+            select(&server.executable, &devices, &[]).await?.as_deref(),
+            Some("CUDA0,CUDA1")
+        );
+        assert_eq!(
+            select(
+                &server.executable,
+                &devices,
+                &[("CUDA_VISIBLE_DEVICES", "1".to_owned())]
+            )
+            .await?
+            .as_deref(),
+            Some("CUDA1")
+        );
+
+        remove(server);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_reports_backend_load_failures() -> Result<(), Error> {
+        let server = fake_server(
+            "failure",
+            "echo 'load_backend: failed to load ./libggml-cuda.so: libcudart.so.12: cannot open shared object file' >&2\n\
+             echo 'Available devices:'\n\
+             echo '  Vulkan0: AMD Radeon (RADV NAVI31) (20464 MiB, 20000 MiB free)'",
+        );
+
+        let DeviceList { devices, failures } = server.devices().await?;
+
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].backend, Backend::Vulkan);
+        assert_eq!(
+            failures,
+            [LoadFailure {
+                library: PathBuf::from("./libggml-cuda.so"),
+                reason: "libcudart.so.12: cannot open shared object file".to_owned(),
+            }]
+        );
+
+        assert_eq!(
+            select(&server.executable, &Devices::Backend(Backend::Vulkan), &[])
+                .await?
+                .as_deref(),
+            Some("Vulkan0")
+        );
+        assert!(matches!(
+            select(&server.executable, &Devices::Backend(Backend::Cuda), &[]).await,
+            Err(Error::BackendLoadFailed { library, .. })
+                if library == Path::new("./libggml-cuda.so")
+        ));
+
+        remove(server);
+
+        Ok(())
+    }
+}
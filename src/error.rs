@@ -1,5 +1,13 @@
+use crate::backend;
+use crate::manifest::Checksum;
+use crate::{Artifact, Backend, Build, Platform};
+
+use std::fmt;
 use std::io;
+use std::path::PathBuf;
+use std::process::ExitStatus;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// An error.
 #[derive(Debug, Clone)]
@@ -8,6 +16,239 @@ pub enum Error {
     IOFailed(Arc<io::Error>),
     /// Some HTTP request failed.
     RequestFailed(Arc<reqwest::Error>),
+    /// Some background task failed to complete.
+    TaskFailed(Arc<tokio::task::JoinError>),
+    /// The current platform is not supported.
+    UnsupportedPlatform {
+        /// The operating system of the platform.
+        os: &'static str,
+        /// The CPU architecture of the platform.
+        arch: &'static str,
+    },
+    /// A [`Build`] is not published in the source.
+    BuildNotFound {
+        /// The [`Build`] requested.
+        build: Build,
+    },
+    /// An [`Artifact`] is not published for the current platform in a [`Build`].
+    ArtifactNotFound {
+        /// The [`Artifact`] requested.
+        artifact: Artifact,
+        /// The [`Build`] requested.
+        build: Build,
+    },
+    /// A downloaded [`Artifact`] is smaller than expected.
+    TruncatedDownload {
+        /// The [`Artifact`] downloaded.
+        artifact: Artifact,
+        /// The expected size, in bytes.
+        expected: u64,
+        /// The amount of bytes received.
+        received: u64,
+    },
+    /// The SHA-256 checksum of a downloaded [`Artifact`] did not match the expected one.
+    ChecksumMismatch {
+        /// The [`Artifact`] downloaded.
+        artifact: Artifact,
+        /// The expected [`Checksum`].
+        expected: Checksum,
+        /// The actual [`Checksum`] of the download.
+        actual: Checksum,
+    },
+    /// A downloaded [`Artifact`] is not signed, but trusted keys were provided.
+    Unsigned {
+        /// The [`Artifact`] downloaded.
+        artifact: Artifact,
+    },
+    /// A downloaded [`Artifact`] is not signed by any of the trusted keys.
+    InvalidSignature {
+        /// The [`Artifact`] downloaded.
+        artifact: Artifact,
+    },
+    /// The archive of a downloaded [`Artifact`] could not be extracted.
+    ArchiveCorrupted {
+        /// The [`Artifact`] downloaded.
+        artifact: Artifact,
+        /// The path of the archive.
+        path: PathBuf,
+        /// The underlying archive error.
+        error: Arc<zip::result::ZipError>,
+    },
+    /// None of the CUDA runtimes published in a [`Build`] can run with the installed
+    /// NVIDIA driver.
+    UnsupportedDriver {
+        /// The installed NVIDIA [`Driver`](backend::Driver), if any.
+        driver: Option<backend::Driver>,
+        /// The major versions of the CUDA runtimes published.
+        runtimes: Vec<u32>,
+    },
+    /// A [`Backend`] is not available in a [`Platform`].
+    UnsupportedBackend {
+        /// The [`Backend`] requested.
+        backend: Backend,
+        /// The [`Platform`] of the build.
+        platform: Platform,
+    },
+    /// A [`Backend`] is built into the server binary, so it cannot be removed.
+    BuiltinBackend {
+        /// The [`Backend`] requested.
+        backend: Backend,
+    },
+    /// A backend library of a [`Server`](crate::Server) could not be loaded.
+    BackendLoadFailed {
+        /// The path of the backend library.
+        library: PathBuf,
+        /// The reason reported by the loader.
+        reason: String,
+    },
+    /// A [`Server`](crate::Server) has no devices of a [`Backend`].
+    DeviceNotFound {
+        /// The [`Backend`] requested.
+        backend: Backend,
+    },
+    /// An executable is not an installed instance of a [`Build`].
+    InstanceNotFound {
+        /// The [`Build`] searched.
+        build: Build,
+        /// The path of the executable.
+        executable: PathBuf,
+    },
+    /// The [`Build`] is being installed by another process.
+    InstallInProgress {
+        /// The [`Build`] being installed.
+        build: Build,
+    },
+    /// The installation was cancelled through a [`Cancellation`](crate::Cancellation).
+    Cancelled,
+    /// The process of an [`Instance`](crate::Instance) exited unexpectedly.
+    ProcessExited {
+        /// The exit status of the process.
+        status: ExitStatus,
+    },
+    /// An [`Instance`](crate::Instance) did not become ready in time.
+    ReadinessTimeout {
+        /// The URL of the [`Instance`](crate::Instance).
+        url: String,
+        /// The time waited.
+        timeout: Duration,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IOFailed(_) => write!(f, "input/output operation failed"),
+            Error::RequestFailed(_) => write!(f, "HTTP request failed"),
+            Error::TaskFailed(_) => write!(f, "background task failed"),
+            Error::UnsupportedPlatform { os, arch } => {
+                write!(f, "platform {os}-{arch} is not supported")
+            }
+            Error::BuildNotFound { build } => write!(f, "build {build} is not published"),
+            Error::ArtifactNotFound { artifact, build } => {
+                write!(f, "{artifact} is not published in build {build}")
+            }
+            Error::TruncatedDownload {
+                artifact,
+                expected,
+                received,
+            } => write!(
+                f,
+                "download of {artifact} is truncated: expected {expected} bytes, got {received}"
+            ),
+            Error::ChecksumMismatch {
+                artifact,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch for {artifact}: expected {expected}, got {actual}"
+            ),
+            Error::Unsigned { artifact } => write!(f, "{artifact} is not signed"),
+            Error::InvalidSignature { artifact } => {
+                write!(f, "{artifact} is not signed by a trusted key")
+            }
+            Error::ArchiveCorrupted { artifact, path, .. } => write!(
+                f,
+                "archive of {artifact} at {path} is corrupted",
+                path = path.display()
+            ),
+            Error::UnsupportedDriver { driver, runtimes } => {
+                let runtimes = runtimes
+                    .iter()
+                    .map(|major| format!("CUDA {major}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                match driver {
+                    Some(driver) => write!(
+                        f,
+                        "NVIDIA driver {driver} does not support any published runtime ({runtimes})"
+                    ),
+                    None => write!(
+                        f,
+                        "no NVIDIA driver found for the published runtimes ({runtimes})"
+                    ),
+                }
+            }
+            Error::UnsupportedBackend { backend, platform } => {
+                write!(f, "{backend} backend is not available in {platform}")
+            }
+            Error::BuiltinBackend { backend } => {
+                write!(f, "{backend} backend is built into the server binary")
+            }
+            Error::BackendLoadFailed { library, reason } => write!(
+                f,
+                "backend library {library} failed to load: {reason}",
+                library = library.display()
+            ),
+            Error::DeviceNotFound { backend } => {
+                write!(f, "no {backend} devices found")
+            }
+            Error::InstanceNotFound { build, executable } => write!(
+                f,
+                "{} is not an installed instance of build {build}",
+                executable.display()
+            ),
+            Error::InstallInProgress { build } => {
+                write!(f, "build {build} is being installed by another process")
+            }
+            Error::Cancelled => write!(f, "installation was cancelled"),
+            Error::ProcessExited { status } => {
+                write!(f, "llama-server exited unexpectedly: {status}")
+            }
+            Error::ReadinessTimeout { url, timeout } => {
+                write!(f, "llama-server at {url} was not ready after {timeout:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IOFailed(error) => Some(error.as_ref()),
+            Error::RequestFailed(error) => Some(error.as_ref()),
+            Error::TaskFailed(error) => Some(error.as_ref()),
+            Error::ArchiveCorrupted { error, .. } => Some(error.as_ref()),
+            Error::UnsupportedPlatform { .. }
+            | Error::BuildNotFound { .. }
+            | Error::ArtifactNotFound { .. }
+            | Error::TruncatedDownload { .. }
+            | Error::ChecksumMismatch { .. }
+            | Error::Unsigned { .. }
+            | Error::InvalidSignature { .. }
+            | Error::UnsupportedDriver { .. }
+            | Error::UnsupportedBackend { .. }
+            | Error::BuiltinBackend { .. }
+            | Error::BackendLoadFailed { .. }
+            | Error::DeviceNotFound { .. }
+            | Error::InstanceNotFound { .. }
+            | Error::InstallInProgress { .. }
+            | Error::Cancelled
+            | Error::ProcessExited { .. }
+            | Error::ReadinessTimeout { .. } => None,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -18,10 +259,31 @@ impl From<io::Error> for Error {
 
 impl From<Error> for io::Error {
     fn from(error: Error) -> Self {
-        match error {
-            Error::IOFailed(error) => io::Error::new(error.kind(), error),
-            Error::RequestFailed(error) => io::Error::other(error),
-        }
+        let kind = match &error {
+            Error::IOFailed(error) => error.kind(),
+            Error::RequestFailed(_)
+            | Error::TaskFailed(_)
+            | Error::ProcessExited { .. }
+            | Error::BackendLoadFailed { .. } => io::ErrorKind::Other,
+            Error::UnsupportedPlatform { .. }
+            | Error::UnsupportedDriver { .. }
+            | Error::UnsupportedBackend { .. }
+            | Error::BuiltinBackend { .. } => io::ErrorKind::Unsupported,
+            Error::BuildNotFound { .. }
+            | Error::ArtifactNotFound { .. }
+            | Error::DeviceNotFound { .. }
+            | Error::InstanceNotFound { .. } => io::ErrorKind::NotFound,
+            Error::TruncatedDownload { .. } => io::ErrorKind::UnexpectedEof,
+            Error::ChecksumMismatch { .. }
+            | Error::Unsigned { .. }
+            | Error::InvalidSignature { .. }
+            | Error::ArchiveCorrupted { .. } => io::ErrorKind::InvalidData,
+            Error::InstallInProgress { .. } => io::ErrorKind::WouldBlock,
+            Error::Cancelled => io::ErrorKind::Interrupted,
+            Error::ReadinessTimeout { .. } => io::ErrorKind::TimedOut,
+        };
+
+        io::Error::new(kind, error)
     }
 }
 
@@ -33,12 +295,6 @@ impl From<reqwest::Error> for Error {
 
 impl From<tokio::task::JoinError> for Error {
     fn from(error: tokio::task::JoinError) -> Self {
-        Error::IOFailed(Arc::new(io::Error::other(error)))
-    }
-}
-
-impl From<zip::result::ZipError> for Error {
-    fn from(error: zip::result::ZipError) -> Self {
-        Error::IOFailed(Arc::new(io::Error::other(error)))
+        Self::TaskFailed(Arc::new(error))
     }
 }
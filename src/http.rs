@@ -1,10 +1,20 @@
 use crate::Error;
 
-use sipper::{Straw, sipper};
+use futures::future::{self, Either};
+use reqwest::{StatusCode, header};
+use serde::de::DeserializeOwned;
+use sipper::{Sender, Sipper, Straw, sipper};
+use tokio::fs;
 use tokio::io::AsyncWrite;
+use tokio::sync::Notify;
+use tokio::time;
 
-use std::sync::LazyLock;
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::pin::pin;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 
 pub fn client() -> reqwest::Client {
     static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
@@ -21,48 +31,460 @@ pub fn client() -> reqwest::Client {
     CLIENT.clone()
 }
 
-pub fn download<'a, W: AsyncWrite + Unpin>(
-    url: impl reqwest::IntoUrl + Send + 'a,
-    writer: &'a mut W,
-) -> impl Straw<(), Progress, Error> + 'a {
-    use tokio::io::AsyncWriteExt;
+/// An HTTP client that retries failed requests.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: reqwest::Client,
+    retry: Retry,
+    rate_limit: RateLimit,
+}
+
+impl Client {
+    pub fn new(inner: reqwest::Client, retry: Retry, rate_limit: RateLimit) -> Self {
+        Self {
+            inner,
+            retry,
+            rate_limit,
+        }
+    }
+
+    pub async fn json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        self.retrying(async || {
+            Ok(self
+                .inner
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?)
+        })
+        .await
+    }
+
+    pub async fn text(&self, url: &str) -> Result<String, Error> {
+        self.retrying(async || {
+            Ok(self
+                .inner
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+        .await
+    }
+
+    /// Downloads the file at the given URL into the given path, resuming any previous
+    /// partial download left there.
+    ///
+    /// Partial downloads are only resumed if the server validates them with an `ETag`
+    /// or a `Last-Modified` header.
+    pub fn resume<'a>(
+        &'a self,
+        url: &'a str,
+        path: &'a Path,
+    ) -> impl Straw<(), Progress, Error> + 'a {
+        sipper(move |mut progress| async move {
+            let mut attempt = 1;
+
+            loop {
+                let mut last = Progress::default();
+
+                let result = resume(&self.inner, &self.rate_limit, url, path)
+                    .with(|progress| {
+                        last = progress;
+                        progress
+                    })
+                    .run(&progress)
+                    .await;
+
+                match result {
+                    Err(error) if self.retry.should_retry(&error, attempt) => {
+                        let delay = self.retry.backoff(attempt);
+
+                        progress
+                            .send(Progress {
+                                speed: 0,
+                                eta: None,
+                                retry: Some(Attempt {
+                                    number: attempt,
+                                    max: self.retry.max_attempts - 1,
+                                    delay,
+                                }),
+                                ..last
+                            })
+                            .await;
+
+                        time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+
+    async fn retrying<T>(&self, request: impl AsyncFn() -> Result<T, Error>) -> Result<T, Error> {
+        let mut attempt = 1;
 
-    sipper(move |mut progress| async move {
-        let mut download = client().get(url).send().await?;
-        let start = Instant::now();
-        let total = download.content_length().unwrap_or_default();
+        loop {
+            match request().await {
+                Err(error) if self.retry.should_retry(&error, attempt) => {
+                    time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
 
-        let mut downloaded = 0;
+impl Default for Client {
+    fn default() -> Self {
+        Self::new(client(), Retry::default(), RateLimit::default())
+    }
+}
 
-        progress
-            .send(Progress {
-                total,
-                downloaded,
-                speed: 0,
-            })
-            .await;
+fn resume<'a>(
+    client: &'a reqwest::Client,
+    rate_limit: &'a RateLimit,
+    url: &'a str,
+    path: &'a Path,
+) -> impl Straw<(), Progress, Error> + 'a {
+    sipper(move |progress| async move {
+        let validator_path = path.with_extension("validator");
 
-        while let Some(chunk) = download.chunk().await? {
-            downloaded += chunk.len() as u64;
-            let speed = (downloaded as f32 / start.elapsed().as_secs_f32()) as u64;
+        let validator = fs::read_to_string(&validator_path).await.ok();
+        let offset = match fs::metadata(path).await {
+            Ok(metadata) if validator.is_some() => metadata.len(),
+            _ => 0,
+        };
+
+        let mut request = client.get(url);
+
+        if offset > 0
+            && let Some(validator) = &validator
+        {
+            request = request
+                .header(header::RANGE, format!("bytes={offset}-"))
+                .header(header::IF_RANGE, validator);
+        }
 
-            progress
-                .send(Progress {
-                    total,
-                    downloaded,
-                    speed,
-                })
-                .await;
+        let mut response = request.send().await?;
 
-            writer.write_all(&chunk).await?;
+        // Partial responses that do not continue the previous download
+        // restart it from scratch
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE
+            || response.status() == StatusCode::PARTIAL_CONTENT
+                && range_start(&response) != Some(offset)
+        {
+            response = client.get(url).send().await?;
         }
 
-        writer.flush().await?;
+        let response = response.error_for_status()?;
+        let is_partial = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+
+        let mut file = if is_partial {
+            fs::OpenOptions::new().append(true).open(path).await?
+        } else {
+            let validator = response
+                .headers()
+                .get(header::ETAG)
+                .or_else(|| response.headers().get(header::LAST_MODIFIED))
+                .and_then(|value| value.to_str().ok());
+
+            if let Some(validator) = validator {
+                fs::write(&validator_path, validator).await?;
+            } else if fs::try_exists(&validator_path).await? {
+                fs::remove_file(&validator_path).await?;
+            }
+
+            fs::File::create(path).await?
+        };
+
+        receive(
+            response,
+            if is_partial { offset } else { 0 },
+            rate_limit,
+            &mut file,
+            progress,
+        )
+        .await?;
+
+        file.sync_all().await?;
+
+        if fs::try_exists(&validator_path).await? {
+            fs::remove_file(&validator_path).await?;
+        }
 
         Ok(())
     })
 }
 
+/// Returns the first byte of the range sent in a partial response, if any.
+fn range_start(response: &reqwest::Response) -> Option<u64> {
+    let range = response
+        .headers()
+        .get(header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+
+    start.trim().parse().ok()
+}
+
+async fn receive<W: AsyncWrite + Unpin>(
+    mut response: reqwest::Response,
+    offset: u64,
+    rate_limit: &RateLimit,
+    writer: &mut W,
+    mut progress: Sender<Progress>,
+) -> Result<(), Error> {
+    use tokio::io::AsyncWriteExt;
+
+    let length = response.content_length();
+    let total = offset + length.unwrap_or_default();
+    let mut meter = Meter::new(offset, total);
+
+    progress.send(meter.progress()).await;
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            // Interrupted bodies are reported as truncated below
+            Err(error) if length.is_some() && (error.is_body() || error.is_decode()) => break,
+            Err(error) => return Err(error.into()),
+        };
+
+        if let Some(update) = meter.advance(chunk.len() as u64) {
+            progress.send(update).await;
+        }
+
+        writer.write_all(&chunk).await?;
+        rate_limit.consume(chunk.len() as u64).await;
+    }
+
+    if let Some(update) = meter.finish() {
+        progress.send(update).await;
+    }
+
+    writer.flush().await?;
+
+    // The validator is kept on failure, so the partial download
+    // can be resumed
+    if length.is_some() && meter.downloaded < total {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("received {} of {total} bytes", meter.downloaded),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Measures the speed of a download over a moving window and throttles
+/// its [`Progress`] updates.
+#[derive(Debug)]
+pub(crate) struct Meter {
+    downloaded: u64,
+    total: u64,
+    samples: VecDeque<(Instant, u64)>,
+    reported: u64,
+}
+
+impl Meter {
+    /// The time window used to compute the download speed.
+    const WINDOW: Duration = Duration::from_secs(3);
+
+    /// The minimum time between two [`Progress`] updates.
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(downloaded: u64, total: u64) -> Self {
+        Self {
+            downloaded,
+            total,
+            samples: VecDeque::from([(Instant::now(), downloaded)]),
+            reported: downloaded,
+        }
+    }
+
+    /// Advances the download by the given amount of bytes, returning a new
+    /// [`Progress`] if enough time has passed or enough bytes were downloaded
+    /// since the last one.
+    pub fn advance(&mut self, bytes: u64) -> Option<Progress> {
+        self.downloaded += bytes;
+
+        let now = Instant::now();
+        let last = self.samples.back().map(|(time, _)| *time).unwrap_or(now);
+
+        let is_due = now.duration_since(last) >= Self::INTERVAL
+            || self.total > 0
+                && (self.downloaded >= self.total
+                    || self.downloaded - self.reported >= self.total / 100);
+
+        if !is_due {
+            return None;
+        }
+
+        Some(self.report(now))
+    }
+
+    /// Returns the final [`Progress`] of the download, unless already reported.
+    pub fn finish(&mut self) -> Option<Progress> {
+        (self.reported != self.downloaded).then(|| self.report(Instant::now()))
+    }
+
+    pub fn progress(&self) -> Progress {
+        let speed = match (self.samples.front(), self.samples.back()) {
+            (Some((start, from)), Some((end, to))) if end > start => {
+                ((to - from) as f64 / end.duration_since(*start).as_secs_f64()) as u64
+            }
+            _ => 0,
+        };
+
+        Progress {
+            downloaded: self.downloaded,
+            total: self.total,
+            speed,
+            eta: eta(self.downloaded, self.total, speed),
+            retry: None,
+        }
+    }
+
+    fn report(&mut self, now: Instant) -> Progress {
+        self.samples.push_back((now, self.downloaded));
+        self.reported = self.downloaded;
+
+        while self.samples.len() > 2
+            && self
+                .samples
+                .get(1)
+                .is_some_and(|(time, _)| now.duration_since(*time) >= Self::WINDOW)
+        {
+            let _ = self.samples.pop_front();
+        }
+
+        self.progress()
+    }
+}
+
+/// A bandwidth limit shared by all the downloads using it.
+///
+/// The limit can be changed at any time, even while downloads are running.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    bucket: Mutex<Bucket>,
+    changed: Notify,
+}
+
+#[derive(Debug, Default)]
+struct Bucket {
+    bytes_per_second: Option<u64>,
+    next: Option<Instant>,
+}
+
+impl RateLimit {
+    /// The maximum burst allowed after a download has been idle.
+    const BURST: Duration = Duration::from_millis(250);
+
+    /// Creates a new [`RateLimit`] of the given bytes per second.
+    ///
+    /// A limit of `0` does not limit anything.
+    pub fn new(bytes_per_second: u64) -> Self {
+        let limit = Self::default();
+        limit.set(Some(bytes_per_second));
+        limit
+    }
+
+    /// Creates a new [`RateLimit`] that does not limit anything.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current limit of the [`RateLimit`], in bytes per second.
+    pub fn get(&self) -> Option<u64> {
+        self.bucket().bytes_per_second
+    }
+
+    /// Changes the limit of the [`RateLimit`], in bytes per second.
+    ///
+    /// `None` or `Some(0)` removes the limit. Downloads waiting on the previous
+    /// limit are woken up to wait on the new one instead.
+    pub fn set(&self, bytes_per_second: Option<u64>) {
+        {
+            let mut bucket = self.bucket();
+
+            bucket.bytes_per_second = bytes_per_second.filter(|limit| *limit > 0);
+            bucket.next = None;
+        }
+
+        self.state.changed.notify_waiters();
+    }
+
+    async fn consume(&self, bytes: u64) {
+        loop {
+            let mut changed = pin!(self.state.changed.notified());
+            let _ = changed.as_mut().enable();
+
+            let Some(deadline) = self.reserve(bytes) else {
+                return;
+            };
+
+            // A new limit invalidates the deadline, so we reserve again
+            if let Either::Left(_) =
+                future::select(pin!(time::sleep_until(deadline.into())), changed).await
+            {
+                return;
+            }
+        }
+    }
+
+    /// Reserves the given bytes in the [`RateLimit`], returning the time when
+    /// they can be consumed, if limited.
+    fn reserve(&self, bytes: u64) -> Option<Instant> {
+        let mut bucket = self.bucket();
+
+        let bytes_per_second = bucket.bytes_per_second?;
+
+        // Idle time only earns a small burst
+        let now = Instant::now();
+        let earliest = now.checked_sub(Self::BURST).unwrap_or(now);
+        let start = bucket.next.map_or(now, |next| next.max(earliest));
+
+        let deadline = start + Duration::from_secs_f64(bytes as f64 / bytes_per_second as f64);
+        bucket.next = Some(deadline);
+
+        Some(deadline)
+    }
+
+    fn bucket(&self) -> MutexGuard<'_, Bucket> {
+        self.state
+            .bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Estimates the remaining time of a download.
+pub(crate) fn eta(downloaded: u64, total: u64, speed: u64) -> Option<Duration> {
+    if speed == 0 || total == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(
+        total.saturating_sub(downloaded) as f64 / speed as f64,
+    ))
+}
+
 /// The progress of an HTTP download.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Progress {
@@ -71,5 +493,92 @@ pub struct Progress {
     /// The total of bytes to download.
     pub total: u64,
     /// The current download speed, in bytes per second.
+    ///
+    /// It is averaged over the last few seconds.
     pub speed: u64,
+    /// The estimated time left to complete the download, if known.
+    pub eta: Option<Duration>,
+    /// The retry [`Attempt`] about to be made, if the download failed.
+    pub retry: Option<Attempt>,
+}
+
+/// A retry attempt of a failed download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempt {
+    /// The number of the retry, starting at 1.
+    pub number: u32,
+    /// The maximum amount of retries.
+    pub max: u32,
+    /// The time to wait before retrying.
+    pub delay: Duration,
+}
+
+/// The retry policy of HTTP requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retry {
+    /// The maximum amount of attempts of a request, including the first one.
+    pub max_attempts: u32,
+    /// The time to wait before the first retry. It doubles after every attempt.
+    pub initial_backoff: Duration,
+    /// The maximum time to wait between attempts.
+    pub max_backoff: Duration,
+    /// The HTTP status codes that can be retried.
+    ///
+    /// Connection errors and timeouts are always retried.
+    pub statuses: Vec<StatusCode>,
+}
+
+impl Retry {
+    /// A [`Retry`] policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    fn should_retry(&self, error: &Error, attempt: u32) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        let error = match error {
+            Error::RequestFailed(error) => error,
+            Error::IOFailed(error) => return error.kind() == io::ErrorKind::UnexpectedEof,
+            _ => return false,
+        };
+
+        match error.status() {
+            Some(status) => self.statuses.contains(&status),
+            None => error.is_connect() || error.is_timeout() || error.is_body(),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+
+        // Equal jitter: somewhere between half and the whole backoff
+        backoff / 2 + backoff.mul_f64(fastrand::f64() / 2.0)
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
 }
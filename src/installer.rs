@@ -0,0 +1,623 @@
+use crate::backend::Backend;
+use crate::cache::{Cache, Component};
+use crate::cpu;
+use crate::http;
+use crate::manifest::{self, Checksum};
+use crate::{
+    Artifact, Asset, Build, Cancellation, Download, Error, Manifest, Phase, Platform, Progress,
+    PublicKey, RateLimit, Release, Retry, Server, Source, backend,
+};
+
+use futures::channel::mpsc;
+use futures::{StreamExt, TryStreamExt, future, stream};
+use sipper::{Sipper, Straw, sipper};
+use tokio::task;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// A configurable installer of [`Server`] builds.
+#[derive(Debug, Clone)]
+pub struct Installer {
+    source: Source,
+    checksums: BTreeMap<Asset, Checksum>,
+    public_keys: Vec<PublicKey>,
+    unverified: bool,
+    wait: bool,
+    platform: Option<Platform>,
+    cpu: Option<cpu::Variant>,
+    probe: backend::Probe,
+    cancellation: Cancellation,
+    concurrency: usize,
+    client: reqwest::Client,
+    retry: Retry,
+    rate_limit: RateLimit,
+}
+
+impl Installer {
+    /// Creates a new [`Installer`] that downloads builds from the given [`Source`].
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            checksums: BTreeMap::new(),
+            public_keys: Vec::new(),
+            unverified: false,
+            wait: true,
+            platform: None,
+            cpu: None,
+            probe: backend::Probe::new(),
+            cancellation: Cancellation::new(),
+            concurrency: 4,
+            client: http::client(),
+            retry: Retry::default(),
+            rate_limit: RateLimit::unlimited(),
+        }
+    }
+
+    /// Sets the expected SHA-256 [`Checksum`] of the given [`Asset`], overriding
+    /// the one published in the [`Manifest`].
+    pub fn checksum(mut self, asset: Asset, sha256: Checksum) -> Self {
+        let _ = self.checksums.insert(asset, sha256);
+        self
+    }
+
+    /// Adds a trusted minisign [`PublicKey`].
+    ///
+    /// Once any key is trusted, every downloaded artifact must be signed by
+    /// one of the trusted keys before being installed.
+    pub fn public_key(mut self, key: PublicKey) -> Self {
+        self.public_keys.push(key);
+        self
+    }
+
+    /// Sets whether the [`Installer`] may download artifacts by their conventional
+    /// file names, without checksums, when the [`Source`] fails to provide the details
+    /// of a release (e.g. the GitHub API is rate limited).
+    ///
+    /// HTTP mirrors that do not publish release details are always downloaded by their
+    /// conventional file names.
+    ///
+    /// By default, the [`Installer`] fails instead.
+    pub fn unverified(mut self, unverified: bool) -> Self {
+        self.unverified = unverified;
+        self
+    }
+
+    /// Sets whether the [`Installer`] should wait for other processes installing
+    /// the same [`Build`] to finish.
+    ///
+    /// If `false`, installing a [`Build`] that is already being installed by another
+    /// process fails with [`Error::InstallInProgress`].
+    ///
+    /// By default, the [`Installer`] waits.
+    pub fn wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Sets the [`Platform`] of the builds installed by the [`Installer`].
+    ///
+    /// Builds for a foreign [`Platform`] can be installed to bundle them, but they are
+    /// not listed by [`Server::list`]. Use [`Installer::list`] to list them.
+    ///
+    /// By default, the [`Installer`] installs builds for the current [`Platform`].
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Sets the CPU [`Variant`](cpu::Variant) of the server binaries installed by the
+    /// [`Installer`].
+    ///
+    /// By default, the [`Installer`] installs the fastest variant published that the
+    /// CPU of the running system supports, falling back to the portable one. Builds for
+    /// a foreign [`Platform`] are always portable.
+    pub fn cpu(mut self, cpu: cpu::Variant) -> Self {
+        self.cpu = Some(cpu);
+        self
+    }
+
+    /// Sets the [`Probe`](backend::Probe) used to inspect the system.
+    ///
+    /// When a [`Build`] publishes multiple CUDA runtimes, the [`Installer`] installs the
+    /// newest one supported by the NVIDIA driver found by the [`Probe`](backend::Probe).
+    /// If no driver is found, the CUDA backend is skipped.
+    pub fn probe(mut self, probe: backend::Probe) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Sets the [`Cancellation`] that aborts the installs of the [`Installer`].
+    ///
+    /// Cancelled installs fail with [`Error::Cancelled`] after removing any
+    /// partial downloads and unfinished components.
+    pub fn cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Sets the maximum amount of artifacts the [`Installer`] downloads at the same time.
+    ///
+    /// By default, the [`Installer`] downloads up to 4 artifacts concurrently.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets the [`reqwest::Client`] used for the HTTP requests of the [`Installer`].
+    ///
+    /// Use it to configure proxies, timeouts, default headers, or custom TLS roots.
+    /// The GitHub API rejects requests without a user agent, so make sure to set one.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sets the [`Retry`] policy of the HTTP requests of the [`Installer`].
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the [`RateLimit`] of the downloads of the [`Installer`].
+    ///
+    /// Keep a clone of the [`RateLimit`] to adjust it while downloading.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Fetches the latest [`Build`] available in the [`Source`] of the [`Installer`].
+    pub async fn latest(&self) -> Result<Build, Error> {
+        self.source.latest(&self.http()).await
+    }
+
+    /// Fetches all the [`Release`] builds published in the [`Source`] of the
+    /// [`Installer`], newest first.
+    pub async fn available(&self) -> Result<Vec<Release>, Error> {
+        let mut releases = self.source.releases(&self.http()).await?;
+        releases.sort_by_key(|release| Reverse(release.build));
+
+        Ok(releases)
+    }
+
+    /// Fetches the [`Manifest`] of the given [`Build`] from the [`Source`] of the
+    /// [`Installer`].
+    pub async fn manifest(&self, build: Build) -> Result<Manifest, Error> {
+        self.source.manifest(build, &self.http()).await
+    }
+
+    /// Downloads and installs the given [`Build`] of a [`Server`] with the given backends.
+    ///
+    /// Backends not published for the [`Build`] are skipped; the [`Server::backends`]
+    /// returned contain only the installed ones.
+    pub fn install(
+        &self,
+        build: Build,
+        backends: backend::Set,
+    ) -> impl Straw<Server, Download, Error> {
+        self.installation(build, backends, backend::Set::empty())
+    }
+
+    /// Installs the given [`Build`] with the given backends, failing if any of the
+    /// `required` ones cannot be installed.
+    fn installation(
+        &self,
+        build: Build,
+        backends: backend::Set,
+        required: backend::Set,
+    ) -> impl Straw<Server, Download, Error> {
+        sipper(async move |sender| {
+            let platform = self.target()?;
+
+            let cache = Cache::with_platform(build, platform);
+
+            let _lock = self
+                .cancellation
+                .guard(async {
+                    if self.wait {
+                        cache.lock().await
+                    } else {
+                        cache.try_lock()?.ok_or(Error::InstallInProgress { build })
+                    }
+                })
+                .await?;
+
+            cache.clean().await?;
+
+            let result = self
+                .cancellation
+                .guard(
+                    self.download(&cache, build, platform, backends, required)
+                        .run(sender),
+                )
+                .await;
+
+            if let Err(Error::Cancelled) = result {
+                // Blocking work keeps running after its future is dropped
+                self.cancellation.settle().await;
+
+                cache.discard().await?;
+            }
+
+            result
+        })
+    }
+
+    /// Downloads and installs the given [`Backend`] into the [`Build`] of the given
+    /// [`Server`], keeping its CPU [`Variant`](cpu::Variant).
+    ///
+    /// The previous instance directory of the [`Server`] is removed.
+    ///
+    /// Fails with [`Error::UnsupportedBackend`] if the [`Backend`] is not available in
+    /// the [`Platform`] of the [`Installer`], and with [`Error::ArtifactNotFound`] if it
+    /// is not published for the [`Build`].
+    pub fn add_backend(
+        &self,
+        server: &Server,
+        backend: Backend,
+    ) -> impl Straw<Server, Download, Error> {
+        let server = server.clone();
+
+        sipper(async move |sender| {
+            let platform = self.target()?;
+
+            if !backend.is_available_on(platform) {
+                return Err(Error::UnsupportedBackend { backend, platform });
+            }
+
+            let installer = self.clone().cpu(server.cpu);
+
+            let updated = installer
+                .installation(
+                    server.build,
+                    server.backends | backend.into(),
+                    backend.into(),
+                )
+                .run(sender)
+                .await?;
+
+            if updated.executable != server.executable {
+                Cache::with_platform(server.build, self.target()?)
+                    .unlink(&server.executable)
+                    .await?;
+            }
+
+            Ok(updated)
+        })
+    }
+
+    /// Removes the given [`Backend`] from the [`Build`] of the given [`Server`],
+    /// installed for the [`Platform`] of the [`Installer`].
+    ///
+    /// Any instance directories of the [`Build`] using the [`Backend`] are removed too.
+    ///
+    /// Fails with [`Error::BuiltinBackend`] if the [`Backend`] is built into the server
+    /// binary.
+    pub async fn remove_backend(&self, server: &Server, backend: Backend) -> Result<Server, Error> {
+        if backend.is_builtin() {
+            return Err(Error::BuiltinBackend { backend });
+        }
+
+        let executable = Cache::with_platform(server.build, self.target()?)
+            .remove_backend(&server.executable, backend)
+            .await?;
+
+        Ok(Server {
+            backends: server.backends.difference(backend.into()),
+            executable,
+            ..server.clone()
+        })
+    }
+
+    /// Lists all the [`Server`] builds installed for the [`Platform`] of the
+    /// [`Installer`].
+    pub async fn list(&self) -> Result<Vec<Build>, Error> {
+        let mut builds: Vec<_> = Cache::list_with_platform(self.target()?)
+            .await?
+            .iter()
+            .map(Cache::build)
+            .collect();
+
+        builds.sort();
+
+        Ok(builds)
+    }
+
+    /// Deletes the [`Server`] installation with the given [`Build`], installed for the
+    /// [`Platform`] of the [`Installer`].
+    pub async fn delete(&self, build: Build) -> Result<(), Error> {
+        Cache::with_platform(build, self.target()?).delete().await
+    }
+
+    fn download<'a>(
+        &'a self,
+        cache: &'a Cache,
+        build: Build,
+        platform: Platform,
+        mut backends: backend::Set,
+        required: backend::Set,
+    ) -> impl Straw<Server, Download, Error> + 'a {
+        sipper(async move |mut sender| {
+            let http = self.http();
+
+            // Mirrors may not publish release details, in which case we fall back
+            // to the conventional file names
+            let (manifest, is_conventional) = match self.source.manifest(build, &http).await {
+                Ok(manifest) => (manifest, false),
+                Err(Error::BuildNotFound { .. }) if matches!(self.source, Source::Http(_)) => {
+                    (self.conventional(build, platform)?, true)
+                }
+                Err(Error::RequestFailed(_)) if self.unverified => {
+                    (self.conventional(build, platform)?, true)
+                }
+                Err(error) => return Err(error),
+            };
+
+            let variants: Vec<_> = match self.cpu {
+                Some(cpu) => vec![cpu],
+                None if Platform::current() == Some(platform) => cpu::Variant::detect().collect(),
+                None => vec![cpu::Variant::Portable],
+            };
+
+            let server = variants
+                .into_iter()
+                .find_map(|cpu| {
+                    self.entry(&manifest, Asset::new(Artifact::Server, platform).cpu(cpu))
+                })
+                .ok_or(Error::ArtifactNotFound {
+                    artifact: Artifact::Server,
+                    build,
+                })?;
+
+            let cpu = server.asset.cpu;
+
+            let cuda = if backends.contains(backend::Set::CUDA)
+                && Backend::Cuda.is_available_on(platform)
+            {
+                match self.cuda(&manifest, platform).await {
+                    Ok(cuda) => cuda,
+                    // CUDA cannot run without a driver, so we skip it
+                    Err(Error::UnsupportedDriver { driver: None, .. })
+                        if !required.contains(backend::Set::CUDA) =>
+                    {
+                        backends.remove(backend::Set::CUDA);
+                        None
+                    }
+                    Err(error) => return Err(error),
+                }
+            } else {
+                None
+            };
+
+            let entries: Vec<_> = [Ok(server)]
+                .into_iter()
+                .chain(
+                    backends
+                        .available_on(platform)
+                        .filter(|backend| !backend.is_builtin())
+                        .filter_map(|backend| {
+                            let artifact = Artifact::Backend(backend);
+                            let asset = Asset::new(artifact, platform);
+
+                            let asset = match cuda {
+                                Some(major) if backend == Backend::Cuda => asset.cuda(major),
+                                _ => asset,
+                            };
+
+                            match self.entry(&manifest, asset) {
+                                Some(entry) => Some(Ok(entry)),
+                                None if required.contains(backend.into()) => {
+                                    Some(Err(Error::ArtifactNotFound { artifact, build }))
+                                }
+                                // Not every backend is published for every build
+                                None => None,
+                            }
+                        }),
+                )
+                .collect::<Result<_, _>>()?;
+
+            let (updates, mut receiver) = mpsc::channel(entries.len());
+
+            // The stream owns the progress updates channel, so the report ends
+            // once all the downloads finish
+            let downloads = stream::iter(&entries)
+                .map({
+                    let http = &http;
+
+                    move |entry| {
+                        let artifact = entry.asset.artifact;
+
+                        let download = cache
+                            .download(http, entry, &self.public_keys, &self.cancellation)
+                            .with(move |progress| (artifact, progress))
+                            .run(&updates);
+
+                        async move {
+                            match download.await {
+                                // Conventional file names are only a guess, so we skip
+                                // any backends that are not published
+                                Err(Error::ArtifactNotFound {
+                                    artifact: Artifact::Backend(backend),
+                                    ..
+                                }) if is_conventional && !required.contains(backend.into()) => {
+                                    Ok(None)
+                                }
+                                Err(Error::ArtifactNotFound {
+                                    artifact: Artifact::Server,
+                                    ..
+                                }) if is_conventional => Err(Error::BuildNotFound { build }),
+                                result => result.map(Some),
+                            }
+                        }
+                    }
+                })
+                .buffer_unordered(self.concurrency.max(1))
+                .try_collect::<Vec<_>>();
+
+            // Installed components are not downloaded again
+            let mut pending = Vec::with_capacity(entries.len());
+
+            for entry in &entries {
+                if !cache.is_installed(Component::new(&entry.asset)).await? {
+                    pending.push(entry);
+                }
+            }
+
+            let report = async {
+                let mut progress: BTreeMap<_, _> = pending
+                    .iter()
+                    .map(|entry| {
+                        (
+                            entry.asset.artifact,
+                            Progress {
+                                total: entry.size,
+                                ..Progress::default()
+                            },
+                        )
+                    })
+                    .collect();
+
+                let mut overall = Progress::default();
+
+                while let Some((artifact, phase)) = receiver.next().await {
+                    if let Phase::Downloading(update) = phase {
+                        let _ = progress.insert(artifact, update);
+                    } else if let Some(progress) = progress.get_mut(&artifact) {
+                        // Finished downloads no longer add to the overall speed
+                        progress.speed = 0;
+                    }
+
+                    overall = progress
+                        .values()
+                        .fold(Progress::default(), |sum, progress| Progress {
+                            downloaded: sum.downloaded + progress.downloaded,
+                            total: sum.total + progress.total,
+                            speed: sum.speed + progress.speed,
+                            eta: None,
+                            retry: None,
+                        });
+
+                    overall.eta = http::eta(overall.downloaded, overall.total, overall.speed);
+
+                    sender
+                        .send(Download {
+                            artifact,
+                            phase,
+                            overall,
+                        })
+                        .await;
+                }
+
+                overall
+            };
+
+            let (components, overall) = future::join(downloads, report).await;
+            let components: Vec<_> = components?.into_iter().flatten().collect();
+
+            let backends = backends
+                .available_on(platform)
+                .filter(|backend| {
+                    backend.is_builtin()
+                        || components
+                            .iter()
+                            .any(|component| component.artifact() == Artifact::Backend(*backend))
+                })
+                .fold(backend::Set::empty(), |backends, backend| {
+                    backends | backend.into()
+                });
+
+            let executable = cache
+                .link(components)
+                .with(|(component, phase)| Download {
+                    artifact: component.artifact(),
+                    phase,
+                    overall,
+                })
+                .run(sender)
+                .await?;
+
+            Ok(Server {
+                build,
+                backends,
+                cpu,
+                executable,
+            })
+        })
+    }
+
+    async fn cuda(&self, manifest: &Manifest, platform: Platform) -> Result<Option<u32>, Error> {
+        let mut runtimes: Vec<_> = manifest
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.asset.artifact == Artifact::Backend(Backend::Cuda)
+                    && entry.asset.platform == platform
+            })
+            .filter_map(|entry| entry.asset.cuda)
+            .collect();
+
+        runtimes.sort_by_key(|major| Reverse(*major));
+
+        if runtimes.is_empty() {
+            return Ok(None);
+        }
+
+        // The driver of a foreign platform cannot be probed, so we bundle
+        // its newest runtime
+        if Platform::current() != Some(platform) {
+            return Ok(runtimes.first().copied());
+        }
+
+        let driver = task::spawn_blocking({
+            let probe = self.probe.clone();
+
+            move || probe.nvidia_driver()
+        })
+        .await?;
+
+        runtimes
+            .iter()
+            .copied()
+            .find(|major| driver.is_some_and(|driver| driver.supports(*major, platform)))
+            .map(Some)
+            .ok_or(Error::UnsupportedDriver { driver, runtimes })
+    }
+
+    fn conventional(&self, build: Build, platform: Platform) -> Result<Manifest, Error> {
+        self.source
+            .conventional(build, platform, self.cpu.unwrap_or_default())
+            .ok_or(Error::BuildNotFound { build })
+    }
+
+    fn entry(&self, manifest: &Manifest, asset: Asset) -> Option<manifest::Entry> {
+        let mut entry = manifest.get(&asset).cloned()?;
+
+        if let Some(sha256) = self.checksums.get(&asset) {
+            entry.sha256 = Some(*sha256);
+        }
+
+        Some(entry)
+    }
+
+    fn target(&self) -> Result<Platform, Error> {
+        match self.platform {
+            Some(platform) => Ok(platform),
+            None => Platform::detect(),
+        }
+    }
+
+    fn http(&self) -> http::Client {
+        http::Client::new(
+            self.client.clone(),
+            self.retry.clone(),
+            self.rate_limit.clone(),
+        )
+    }
+}
+
+impl Default for Installer {
+    fn default() -> Self {
+        Self::new(Source::default())
+    }
+}
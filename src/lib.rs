@@ -1,17 +1,33 @@
 //! Download, embed, and run llama.cpp in your Rust projects.
 pub mod backend;
+pub mod cpu;
+pub mod manifest;
 
 mod artifact;
 mod build;
 mod cache;
+mod cancellation;
+mod device;
 mod error;
 mod http;
+mod installer;
+mod lock;
+mod platform;
+mod signature;
+mod source;
 
-pub use artifact::Artifact;
+pub use artifact::{Artifact, Asset};
 pub use backend::Backend;
-pub use build::Build;
+pub use build::{Build, Release};
+pub use cancellation::Cancellation;
+pub use device::{Device, DeviceList, Devices, LoadFailure, SplitMode};
 pub use error::Error;
-pub use http::Progress;
+pub use http::{Attempt, Progress, RateLimit, Retry};
+pub use installer::Installer;
+pub use manifest::Manifest;
+pub use platform::Platform;
+pub use signature::PublicKey;
+pub use source::Source;
 
 use crate::cache::Cache;
 
@@ -19,7 +35,6 @@ use sipper::{Sipper, Straw, sipper};
 use tokio::process;
 use tokio::time::{self, Duration};
 
-use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
@@ -30,12 +45,17 @@ pub struct Server {
     pub build: Build,
     /// The available backends of the [`Server`].
     pub backends: backend::Set,
+    /// The CPU [`Variant`](cpu::Variant) of the [`Server`] binary.
+    pub cpu: cpu::Variant,
     /// The path to the executable binary of the [`Server`].
     pub executable: PathBuf,
 }
 
 impl Server {
-    /// Lists all the [`Server`] builds installed in the system.
+    /// Lists all the [`Server`] builds installed in the system for the current
+    /// [`Platform`].
+    ///
+    /// Use [`Installer::list`] for builds installed for other platforms.
     pub async fn list() -> Result<Vec<Build>, Error> {
         let mut builds: Vec<_> = Cache::list().await?.iter().map(Cache::build).collect();
 
@@ -44,35 +64,18 @@ impl Server {
         Ok(builds)
     }
 
-    /// Download and installs the given [`Build`] of a [`Server`] with the given backends.
-    pub fn download(build: Build, backends: backend::Set) -> impl Straw<Self, Download, Error> {
-        sipper(async move |sender| {
-            let cache = Cache::new(build);
-
-            let artifacts = [Artifact::Server]
-                .into_iter()
-                .chain(backends.available().map(Artifact::Backend));
-
-            let mut components = Vec::new();
-
-            for artifact in artifacts {
-                let component = cache
-                    .download(artifact)
-                    .with(|progress| Download { artifact, progress })
-                    .run(sender.clone())
-                    .await?;
-
-                components.push(component);
-            }
-
-            let executable = cache.link(components).await?;
-
-            Ok(Self {
-                build,
-                backends: backends.normalize(),
-                executable,
-            })
-        })
+    /// Download and installs the given [`Build`] of a [`Server`] with the given backends
+    /// from the given [`Source`].
+    ///
+    /// Use an [`Installer`] for further configuration.
+    pub fn download(
+        source: &Source,
+        build: Build,
+        backends: backend::Set,
+    ) -> impl Straw<Self, Download, Error> {
+        let installer = Installer::new(source.clone());
+
+        sipper(async move |sender| installer.install(build, backends).run(sender).await)
     }
 
     /// Boots an [`Instance`] of the [`Server`] using the given model.
@@ -81,17 +84,41 @@ impl Server {
         model: impl AsRef<Path>,
         settings: Settings,
     ) -> Result<Instance, Error> {
-        let process = process::Command::new(&self.executable)
-            .args(
-                format!(
-                    "--model {model} --host {host} --port {port} --gpu-layers {gpu_layers} --jinja",
-                    model = model.as_ref().display(),
-                    host = settings.host,
-                    port = settings.port,
-                    gpu_layers = settings.gpu_layers,
-                )
-                .split_whitespace(),
+        let mut command = process::Command::new(&self.executable);
+
+        let _ = command.args(
+            format!(
+                "--model {model} --host {host} --port {port} --gpu-layers {gpu_layers} --jinja",
+                model = model.as_ref().display(),
+                host = settings.host,
+                port = settings.port,
+                gpu_layers = settings.gpu_layers,
             )
+            .split_whitespace(),
+        );
+
+        let environment = settings.environment();
+
+        if let Some(devices) =
+            device::select(&self.executable, &settings.devices, &environment).await?
+        {
+            let _ = command.args(["--device", &devices]);
+        }
+
+        if let Some(main_gpu) = settings.main_gpu {
+            let _ = command.args(["--main-gpu", &main_gpu.to_string()]);
+        }
+
+        if let Some(split_mode) = settings.split_mode {
+            let _ = command.args(["--split-mode", split_mode.name()]);
+        }
+
+        if !settings.tensor_split.is_empty() {
+            let _ = command.args(["--tensor-split", &join(&settings.tensor_split)]);
+        }
+
+        let process = command
+            .envs(environment)
             .stdin(settings.stdin)
             .stdout(settings.stdout)
             .stderr(settings.stderr)
@@ -105,7 +132,56 @@ impl Server {
         })
     }
 
-    /// Deletes the [`Server`] installation with the given [`Build`].
+    /// Lists the compute [`Device`]s visible to the [`Server`], along with any backend
+    /// libraries that failed to load (e.g. because of a missing runtime).
+    pub async fn devices(&self) -> Result<DeviceList, Error> {
+        device::list(&self.executable, &[]).await
+    }
+
+    /// Downloads and installs the given [`Backend`] into the [`Server`] from the given
+    /// [`Source`].
+    ///
+    /// Use an [`Installer`] for further configuration.
+    pub fn add_backend(
+        &self,
+        source: &Source,
+        backend: Backend,
+    ) -> impl Straw<Self, Download, Error> {
+        let installer = Installer::new(source.clone());
+        let server = self.clone();
+
+        sipper(async move |sender| installer.add_backend(&server, backend).run(sender).await)
+    }
+
+    /// Removes the given [`Backend`] from the [`Server`] installation.
+    ///
+    /// Any instance directories of the [`Build`] using the [`Backend`] are removed too.
+    ///
+    /// Only builds installed for the current [`Platform`] are supported. Use
+    /// [`Installer::remove_backend`] for builds installed for other platforms.
+    ///
+    /// Fails with [`Error::BuiltinBackend`] if the [`Backend`] is built into the server
+    /// binary.
+    pub async fn remove_backend(&self, backend: Backend) -> Result<Self, Error> {
+        if backend.is_builtin() {
+            return Err(Error::BuiltinBackend { backend });
+        }
+
+        let executable = Cache::new(self.build)
+            .remove_backend(&self.executable, backend)
+            .await?;
+
+        Ok(Self {
+            backends: self.backends.difference(backend.into()),
+            executable,
+            ..self.clone()
+        })
+    }
+
+    /// Deletes the [`Server`] installation with the given [`Build`] for the current
+    /// [`Platform`].
+    ///
+    /// Use [`Installer::delete`] for builds installed for other platforms.
     pub async fn delete(build: Build) -> Result<(), Error> {
         Cache::new(build).delete().await
     }
@@ -120,6 +196,21 @@ pub struct Settings {
     pub port: u32,
     /// The amount of layers to run in a GPU backend.
     pub gpu_layers: u32,
+    /// The compute [`Devices`] to run in.
+    pub devices: Devices,
+    /// The index of the device used for the whole model when using
+    /// [`SplitMode::Single`], or for intermediate results otherwise.
+    pub main_gpu: Option<u32>,
+    /// How the model is split across multiple devices.
+    pub split_mode: Option<SplitMode>,
+    /// The proportion of the model offloaded to each device (e.g. `[3.0, 1.0]`).
+    pub tensor_split: Vec<f32>,
+    /// The indices of the NVIDIA GPUs visible to the [`Instance`]
+    /// (i.e. `CUDA_VISIBLE_DEVICES`).
+    pub cuda_visible_devices: Option<Vec<u32>>,
+    /// The indices of the AMD GPUs visible to the [`Instance`]
+    /// (i.e. `HIP_VISIBLE_DEVICES`).
+    pub hip_visible_devices: Option<Vec<u32>>,
     /// The standard input stream.
     pub stdin: Stdio,
     /// The standard output stream.
@@ -128,12 +219,32 @@ pub struct Settings {
     pub stderr: Stdio,
 }
 
+impl Settings {
+    /// Returns the environment variables restricting the devices visible to
+    /// an [`Instance`].
+    fn environment(&self) -> Vec<(&'static str, String)> {
+        [
+            ("CUDA_VISIBLE_DEVICES", &self.cuda_visible_devices),
+            ("HIP_VISIBLE_DEVICES", &self.hip_visible_devices),
+        ]
+        .into_iter()
+        .filter_map(|(name, devices)| Some((name, join(devices.as_ref()?))))
+        .collect()
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             host: "127.0.0.1".to_owned(),
             port: 8080,
             gpu_layers: 80,
+            devices: Devices::All,
+            main_gpu: None,
+            split_mode: None,
+            tensor_split: Vec::new(),
+            cuda_visible_devices: None,
+            hip_visible_devices: None,
             stdin: Stdio::null(),
             stdout: Stdio::null(),
             stderr: Stdio::null(),
@@ -162,9 +273,7 @@ impl Instance {
     pub async fn wait_until_ready(&mut self) -> Result<(), Error> {
         loop {
             if let Some(status) = self.process.try_wait()? {
-                return Err(io::Error::other(format!(
-                    "llama-server exited unexpectedly: {status}"
-                )))?;
+                return Err(Error::ProcessExited { status });
             }
 
             if let Ok(response) = http::client()
@@ -181,15 +290,59 @@ impl Instance {
 
         Ok(())
     }
+
+    /// Waits until the [`Instance`] is warmed up and ready to receive requests,
+    /// failing with [`Error::ReadinessTimeout`] if it takes longer than the given
+    /// duration.
+    pub async fn wait_until_ready_for(&mut self, timeout: Duration) -> Result<(), Error> {
+        let url = self.url();
+
+        time::timeout(timeout, self.wait_until_ready())
+            .await
+            .map_err(|_| Error::ReadinessTimeout { url, timeout })?
+    }
+}
+
+fn join(values: &[impl ToString]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 /// The download state of a [`Server`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Download {
-    /// The [`Artifact`] being downloaded.
+    /// The [`Artifact`] being installed.
     pub artifact: Artifact,
-    /// The download [`Progress`].
-    pub progress: Progress,
+    /// The current installation [`Phase`] of the [`Artifact`].
+    pub phase: Phase,
+    /// The combined download [`Progress`] of all the artifacts.
+    pub overall: Progress,
+}
+
+/// The installation phase of an [`Artifact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The [`Artifact`] is being downloaded.
+    Downloading(Progress),
+    /// The checksum and signature of the [`Artifact`] are being verified.
+    Verifying,
+    /// The files of the [`Artifact`] are being extracted.
+    Extracting {
+        /// The amount of files extracted.
+        extracted: usize,
+        /// The total of files in the [`Artifact`].
+        total: usize,
+    },
+    /// The files of the [`Artifact`] are being linked into the [`Server`] directory.
+    Linking {
+        /// The amount of files linked.
+        linked: usize,
+        /// The total of files to link.
+        total: usize,
+    },
 }
 
 #[cfg(test)]
@@ -197,7 +350,6 @@ mod tests {
     use super::*;
 
     use tokio::fs;
-    use tokio::io;
 
     #[tokio::test]
     #[ignore]
@@ -212,22 +364,21 @@ mod tests {
             assert!(installed.is_empty());
         }
 
-        let build = Build::latest().await.unwrap_or(Build::locked(6730));
-        let server = Server::download(build, backend::Set::all()).await?;
+        let source = Source::default();
+        let build = Build::latest(&source).await.unwrap_or(Build::locked(6730));
+        let server = Server::download(&source, build, backend::Set::all()).await?;
 
         assert_eq!(server.build, build);
         assert_eq!(
             server.backends,
-            if cfg!(target_os = "macos") {
-                backend::Set::empty()
-            } else {
-                backend::Set::all()
-            }
+            (backend::Set::CUDA | backend::Set::HIP | backend::Set::CPU | backend::Set::METAL)
+                .normalize()
         );
 
         if !fs::try_exists(MODEL_FILE).await? {
-            let model = fs::File::create(MODEL_FILE).await?;
-            http::download(MODEL_URL, &mut io::BufWriter::new(model)).await?;
+            http::Client::default()
+                .resume(MODEL_URL, Path::new(MODEL_FILE))
+                .await?;
         }
 
         let mut instance = server
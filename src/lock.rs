@@ -0,0 +1,56 @@
+use crate::Error;
+
+use tokio::task;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An advisory, cross-process file lock.
+///
+/// The lock is released when dropped.
+#[derive(Debug)]
+pub struct Lock {
+    file: fs::File,
+}
+
+impl Lock {
+    /// Acquires the lock at the given path, waiting for any other holder to release it.
+    pub async fn acquire(path: PathBuf) -> Result<Self, Error> {
+        task::spawn_blocking(move || {
+            let file = open(&path)?;
+            file.lock()?;
+
+            Ok(Self { file })
+        })
+        .await?
+    }
+
+    /// Acquires the lock at the given path, unless it is already held.
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>, Error> {
+        let file = open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { file })),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(error)) => Err(error.into()),
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn open(path: &Path) -> Result<fs::File, Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?)
+}
@@ -0,0 +1,146 @@
+//! Inspect the artifacts published for a build.
+use crate::http;
+use crate::source;
+use crate::{Asset, Build, Error, Installer, Progress, Source};
+
+use sipper::{Sipper, Straw, sipper};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The list of assets published for a specific [`Build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// The [`Build`] of the [`Manifest`].
+    pub build: Build,
+    /// The published entries.
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Fetches the [`Manifest`] of the given [`Build`] from the given [`Source`].
+    pub async fn fetch(source: &Source, build: Build) -> Result<Self, Error> {
+        Installer::new(source.clone()).manifest(build).await
+    }
+
+    /// Returns the [`Entry`] of the given [`Asset`], if published.
+    pub fn get(&self, asset: &Asset) -> Option<&Entry> {
+        self.entries.iter().find(|entry| &entry.asset == asset)
+    }
+}
+
+/// A published [`Asset`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The [`Asset`] of the [`Entry`].
+    pub asset: Asset,
+    /// The size of the [`Entry`], in bytes.
+    pub size: u64,
+    /// The URL where the [`Entry`] can be downloaded from.
+    ///
+    /// Local files use the `file://` scheme.
+    pub url: String,
+    /// The published SHA-256 [`Checksum`] of the [`Entry`], if any.
+    pub sha256: Option<Checksum>,
+    /// The URL of the detached minisign signature of the [`Entry`], if any.
+    pub signature: Option<String>,
+}
+
+impl Entry {
+    pub(crate) async fn fetch_signature(
+        &self,
+        http: &http::Client,
+    ) -> Result<Option<String>, Error> {
+        let Some(url) = &self.signature else {
+            return Ok(None);
+        };
+
+        let signature = if let Some(path) = url.strip_prefix("file://") {
+            tokio::fs::read_to_string(path).await?
+        } else {
+            match http.text(url).await {
+                Ok(signature) => signature,
+                Err(Error::RequestFailed(error))
+                    if error.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+                {
+                    return Ok(None);
+                }
+                Err(error) => return Err(error),
+            }
+        };
+
+        Ok(Some(signature))
+    }
+
+    pub(crate) fn download<'a>(
+        &'a self,
+        http: &'a http::Client,
+        path: &'a Path,
+    ) -> impl Straw<(), Progress, Error> + 'a {
+        sipper(async move |sender| {
+            if let Some(file) = self.url.strip_prefix("file://") {
+                let mut writer = tokio::fs::File::create(path).await?;
+
+                source::copy(file.into(), &mut writer).run(sender).await
+            } else {
+                http.resume(&self.url, path).run(sender).await
+            }
+        })
+    }
+}
+
+/// A SHA-256 checksum.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checksum([u8; 32]);
+
+impl Checksum {
+    /// Creates a [`Checksum`] from its raw bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the raw bytes of the [`Checksum`].
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = io::Error;
+
+    fn from_str(checksum: &str) -> Result<Self, Self::Err> {
+        let hex = checksum.trim().trim_start_matches("sha256:");
+
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(io::Error::other(format!("invalid checksum: {checksum}")));
+        }
+
+        let mut bytes = [0; 32];
+
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            *byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| io::Error::other(format!("invalid checksum: {checksum}")))?;
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checksum({self})")
+    }
+}
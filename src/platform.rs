@@ -0,0 +1,104 @@
+use crate::Error;
+
+use std::env;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// An operating system and CPU architecture pair that builds are published for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Platform {
+    /// Linux on x86-64.
+    LinuxX64,
+    /// Linux on ARM64.
+    LinuxArm64,
+    /// macOS on x86-64.
+    MacosX64,
+    /// macOS on Apple Silicon.
+    MacosArm64,
+    /// Windows on x86-64.
+    WindowsX64,
+    /// Windows on ARM64.
+    WindowsArm64,
+}
+
+impl Platform {
+    /// All the supported platforms.
+    pub const ALL: &[Self] = &[
+        Self::LinuxX64,
+        Self::LinuxArm64,
+        Self::MacosX64,
+        Self::MacosArm64,
+        Self::WindowsX64,
+        Self::WindowsArm64,
+    ];
+
+    /// Returns the [`Platform`] the program is running on, if supported.
+    pub fn current() -> Option<Self> {
+        match (env::consts::OS, env::consts::ARCH) {
+            ("linux", "x86_64") => Some(Self::LinuxX64),
+            ("linux", "aarch64") => Some(Self::LinuxArm64),
+            ("macos", "x86_64") => Some(Self::MacosX64),
+            ("macos", "aarch64") => Some(Self::MacosArm64),
+            ("windows", "x86_64") => Some(Self::WindowsX64),
+            ("windows", "aarch64") => Some(Self::WindowsArm64),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the [`Platform`] used in published file names (e.g. `linux-x64`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::LinuxX64 => "linux-x64",
+            Self::LinuxArm64 => "linux-arm64",
+            Self::MacosX64 => "macos-x64",
+            Self::MacosArm64 => "macos-arm64",
+            Self::WindowsX64 => "windows-x64",
+            Self::WindowsArm64 => "windows-arm64",
+        }
+    }
+
+    /// Returns whether the [`Platform`] runs macOS.
+    pub fn is_macos(self) -> bool {
+        matches!(self, Self::MacosX64 | Self::MacosArm64)
+    }
+
+    /// Returns whether the [`Platform`] runs Windows.
+    pub fn is_windows(self) -> bool {
+        matches!(self, Self::WindowsX64 | Self::WindowsArm64)
+    }
+
+    /// Returns the file name of the `llama-server` executable in the [`Platform`].
+    pub fn executable(self) -> &'static str {
+        if self.is_windows() {
+            "llama-server.exe"
+        } else {
+            "llama-server"
+        }
+    }
+
+    pub(crate) fn detect() -> Result<Self, Error> {
+        Self::current().ok_or(Error::UnsupportedPlatform {
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+        })
+    }
+}
+
+impl FromStr for Platform {
+    type Err = io::Error;
+
+    fn from_str(platform: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|candidate| candidate.name() == platform)
+            .ok_or_else(|| io::Error::other(format!("invalid platform: {platform}")))
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A trusted [minisign](https://jedisct1.github.io/minisign/) public key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey(minisign_verify::PublicKey);
+
+impl PublicKey {
+    /// Parses a [`PublicKey`] from its base64 encoding.
+    pub fn from_base64(key: &str) -> Result<Self, io::Error> {
+        minisign_verify::PublicKey::from_base64(key.trim())
+            .map(Self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+
+    /// Parses a [`PublicKey`] from the contents of a `minisign.pub` file.
+    pub fn decode(contents: &str) -> Result<Self, io::Error> {
+        minisign_verify::PublicKey::decode(contents)
+            .map(Self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = io::Error;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        if key.trim().lines().count() > 1 {
+            Self::decode(key)
+        } else {
+            Self::from_base64(key)
+        }
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PublicKey")
+            .field(&self.0.untrusted_comment().unwrap_or_default())
+            .finish()
+    }
+}
+
+/// Returns whether the file at the given path has the given signature by any of
+/// the given keys.
+pub(crate) fn verify(path: &Path, signature: &str, keys: &[PublicKey]) -> Result<bool, io::Error> {
+    let Ok(signature) = minisign_verify::Signature::decode(signature) else {
+        return Ok(false);
+    };
+
+    for PublicKey(key) in keys {
+        let Ok(mut verifier) = key.verify_stream(&signature) else {
+            continue;
+        };
+
+        let mut file = std::fs::File::open(path)?;
+        let mut buffer = vec![0; 64 * 1024];
+
+        loop {
+            let read = file.read(&mut buffer)?;
+
+            if read == 0 {
+                break;
+            }
+
+            verifier.update(&buffer[..read]);
+        }
+
+        return Ok(verifier.finalize().is_ok());
+    }
+
+    Ok(false)
+}
@@ -0,0 +1,426 @@
+use crate::cpu;
+use crate::http::{self, Meter};
+use crate::manifest::{self, Manifest};
+use crate::{Artifact, Asset, Backend, Build, Error, Platform, Progress, Release};
+
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use sipper::{Straw, sipper};
+use tokio::fs;
+use tokio::io::{self, AsyncWrite};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The location where builds of `llama-server` are published.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// A GitHub repository publishing every build as a release (e.g. `hecrj/llama-server`).
+    ///
+    /// Minisign signatures are expected as `{file}.minisig` assets.
+    ///
+    /// The details of each release are fetched from the GitHub API, which is rate
+    /// limited. See [`Installer::unverified`](crate::Installer::unverified).
+    GitHub(String),
+    /// An HTTP server mirroring the builds.
+    ///
+    /// Artifacts are expected at `{url}/{build}/{file}`, the tag of the
+    /// latest build at `{url}/latest`, and the list of releases at
+    /// `{url}/releases.json` (in the same format as the GitHub releases API).
+    ///
+    /// The details of each release may be published at `{url}/{build}/release.json`.
+    /// Otherwise, artifacts are downloaded by their conventional file names without
+    /// checksums.
+    Http(String),
+    /// A local directory mirroring the builds.
+    ///
+    /// Artifacts are expected at `{path}/{build}/{file}` and, optionally,
+    /// their SHA-256 checksums at `{path}/{build}/{file}.sha256` and their
+    /// minisign signatures at `{path}/{build}/{file}.minisig`.
+    Directory(PathBuf),
+}
+
+impl Source {
+    /// Creates a [`Source`] pointing to the releases of the given GitHub repository.
+    pub fn github(repository: impl Into<String>) -> Self {
+        Self::GitHub(repository.into())
+    }
+
+    /// Creates a [`Source`] pointing to the given HTTP base URL.
+    pub fn http(url: impl Into<String>) -> Self {
+        Self::Http(url.into().trim_end_matches('/').to_owned())
+    }
+
+    /// Creates a [`Source`] pointing to the given local directory.
+    pub fn directory(path: impl Into<PathBuf>) -> Self {
+        Self::Directory(path.into())
+    }
+
+    pub(crate) async fn latest(&self, http: &http::Client) -> Result<Build, Error> {
+        use serde::Deserialize;
+
+        match self {
+            Self::GitHub(repository) => {
+                #[derive(Deserialize)]
+                struct Release {
+                    tag_name: String,
+                }
+
+                let latest_release_url =
+                    format!("https://api.github.com/repos/{repository}/releases/latest");
+
+                let Release { tag_name } = http.json(&latest_release_url).await?;
+
+                Ok(tag_name.parse()?)
+            }
+            Self::Http(url) => {
+                let tag_name = http.text(&format!("{url}/latest")).await?;
+
+                Ok(tag_name.trim().parse()?)
+            }
+            Self::Directory(path) => {
+                let mut latest = None;
+                let mut read_dir = fs::read_dir(path).await?;
+
+                while let Some(entry) = read_dir.next_entry().await? {
+                    if !entry.file_type().await?.is_dir() {
+                        continue;
+                    }
+
+                    let Ok(build) = entry.file_name().to_string_lossy().parse::<Build>() else {
+                        continue;
+                    };
+
+                    latest = latest.max(Some(build));
+                }
+
+                Ok(latest.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no builds found in {}", path.display()),
+                    )
+                })?)
+            }
+        }
+    }
+
+    pub(crate) async fn releases(&self, http: &http::Client) -> Result<Vec<Release>, Error> {
+        match self {
+            Self::GitHub(repository) => {
+                const PER_PAGE: usize = 100;
+
+                let mut releases = Vec::new();
+
+                for page in 1.. {
+                    let page: Vec<GitHubRelease> = http
+                        .json(&format!(
+                            "https://api.github.com/repos/{repository}/releases?per_page={PER_PAGE}&page={page}"
+                        ))
+                        .await?;
+
+                    let is_last = page.len() < PER_PAGE;
+                    releases.extend(page.into_iter().filter_map(GitHubRelease::parse));
+
+                    if is_last {
+                        break;
+                    }
+                }
+
+                Ok(releases)
+            }
+            Self::Http(url) => {
+                let releases: Vec<GitHubRelease> =
+                    http.json(&format!("{url}/releases.json")).await?;
+
+                Ok(releases
+                    .into_iter()
+                    .filter_map(GitHubRelease::parse)
+                    .collect())
+            }
+            Self::Directory(path) => {
+                let mut releases = Vec::new();
+                let mut read_dir = fs::read_dir(path).await?;
+
+                while let Some(entry) = read_dir.next_entry().await? {
+                    if !entry.file_type().await?.is_dir() {
+                        continue;
+                    }
+
+                    let Ok(build) = entry.file_name().to_string_lossy().parse::<Build>() else {
+                        continue;
+                    };
+
+                    let published_at = entry.metadata().await?.modified()?.into();
+
+                    let mut assets = BTreeSet::new();
+                    let mut read_release = fs::read_dir(entry.path()).await?;
+
+                    while let Some(file) = read_release.next_entry().await? {
+                        if let Some(asset) =
+                            Asset::parse(build, &file.file_name().to_string_lossy())
+                        {
+                            let _ = assets.insert(asset);
+                        }
+                    }
+
+                    releases.push(Release {
+                        build,
+                        published_at,
+                        assets,
+                    });
+                }
+
+                Ok(releases)
+            }
+        }
+    }
+
+    pub(crate) async fn manifest(
+        &self,
+        build: Build,
+        http: &http::Client,
+    ) -> Result<Manifest, Error> {
+        let not_found = |error: Error| match error {
+            Error::RequestFailed(error) if error.status() == Some(StatusCode::NOT_FOUND) => {
+                Error::BuildNotFound { build }
+            }
+            Error::IOFailed(error) if error.kind() == io::ErrorKind::NotFound => {
+                Error::BuildNotFound { build }
+            }
+            error => error,
+        };
+
+        let entries = match self {
+            Self::GitHub(repository) => {
+                let release: GitHubRelease = http
+                    .json(&format!(
+                        "https://api.github.com/repos/{repository}/releases/tags/{build}"
+                    ))
+                    .await
+                    .map_err(not_found)?;
+
+                release.entries(build, |asset| asset.browser_download_url.clone())
+            }
+            Self::Http(url) => {
+                let release: GitHubRelease = http
+                    .json(&format!("{url}/{build}/release.json"))
+                    .await
+                    .map_err(not_found)?;
+
+                release.entries(build, |asset| format!("{url}/{build}/{}", asset.name))
+            }
+            Self::Directory(path) => {
+                let directory = path.join(build.to_string());
+
+                let mut entries = Vec::new();
+                let mut read_dir = fs::read_dir(&directory)
+                    .await
+                    .map_err(|error| not_found(error.into()))?;
+
+                while let Some(file) = read_dir.next_entry().await? {
+                    let name = file.file_name().to_string_lossy().into_owned();
+
+                    let Some(asset) = Asset::parse(build, &name) else {
+                        continue;
+                    };
+
+                    let sha256 =
+                        match fs::read_to_string(directory.join(format!("{name}.sha256"))).await {
+                            Ok(sha256) => Some(
+                                sha256
+                                    .split_whitespace()
+                                    .next()
+                                    .unwrap_or_default()
+                                    .parse()?,
+                            ),
+                            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+                            Err(error) => return Err(error.into()),
+                        };
+
+                    let signature = directory.join(format!("{name}.minisig"));
+
+                    entries.push(manifest::Entry {
+                        asset,
+                        size: file.metadata().await?.len(),
+                        url: format!("file://{}", file.path().display()),
+                        sha256,
+                        signature: fs::try_exists(&signature)
+                            .await?
+                            .then(|| format!("file://{}", signature.display())),
+                    });
+                }
+
+                entries
+            }
+        };
+
+        Ok(Manifest { build, entries })
+    }
+
+    /// Returns a [`Manifest`] listing the conventional file names of the given [`Build`]
+    /// in the given [`Platform`], for sources that may not publish one.
+    ///
+    /// The artifacts in it are not guaranteed to exist.
+    pub(crate) fn conventional(
+        &self,
+        build: Build,
+        platform: Platform,
+        cpu: cpu::Variant,
+    ) -> Option<Manifest> {
+        let url = match self {
+            Self::GitHub(repository) => {
+                format!("https://github.com/{repository}/releases/download/{build}")
+            }
+            Self::Http(url) => format!("{url}/{build}"),
+            Self::Directory(_) => return None,
+        };
+
+        let assets = [Asset::new(Artifact::Server, platform).cpu(cpu)]
+            .into_iter()
+            .chain(
+                Backend::ALL
+                    .iter()
+                    .filter(|backend| !backend.is_builtin() && backend.is_available_on(platform))
+                    .map(|backend| Asset::new(Artifact::Backend(*backend), platform)),
+            );
+
+        let entries = assets
+            .map(|asset| {
+                let url = format!("{url}/{}", asset.file_name(build));
+
+                manifest::Entry {
+                    asset,
+                    size: 0,
+                    signature: Some(format!("{url}.minisig")),
+                    url,
+                    sha256: None,
+                }
+            })
+            .collect();
+
+        Some(Manifest { build, entries })
+    }
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::github("hecrj/llama-server")
+    }
+}
+
+impl FromStr for Source {
+    type Err = io::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = source.strip_prefix("file://") {
+            return Ok(Self::directory(path));
+        }
+
+        if source.starts_with("http://") || source.starts_with("https://") {
+            return Ok(Self::http(source));
+        }
+
+        if let Some(repository) = source.strip_prefix("github:") {
+            return Ok(Self::github(repository));
+        }
+
+        Err(io::Error::other(format!("invalid source: {source}")))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(serde::Deserialize)]
+struct GitHubAsset {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    browser_download_url: String,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+impl GitHubRelease {
+    fn entries(&self, build: Build, url: impl Fn(&GitHubAsset) -> String) -> Vec<manifest::Entry> {
+        let signatures: BTreeMap<_, _> = self
+            .assets
+            .iter()
+            .filter_map(|asset| Some((asset.name.strip_suffix(".minisig")?, url(asset))))
+            .collect();
+
+        self.assets
+            .iter()
+            .filter_map(|asset| {
+                Some(manifest::Entry {
+                    asset: Asset::parse(build, &asset.name)?,
+                    size: asset.size,
+                    url: url(asset),
+                    sha256: asset
+                        .digest
+                        .as_deref()
+                        .and_then(|digest| digest.parse().ok()),
+                    signature: signatures.get(asset.name.as_str()).cloned(),
+                })
+            })
+            .collect()
+    }
+
+    fn parse(self) -> Option<Release> {
+        let build = self.tag_name.parse().ok()?;
+
+        Some(Release {
+            build,
+            published_at: self.published_at?,
+            assets: self
+                .assets
+                .iter()
+                .filter_map(|asset| Asset::parse(build, &asset.name))
+                .collect(),
+        })
+    }
+}
+
+pub(crate) fn copy<'a, W: AsyncWrite + Unpin>(
+    path: PathBuf,
+    writer: &'a mut W,
+) -> impl Straw<(), Progress, Error> + 'a {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    sipper(move |mut progress| async move {
+        let mut file = fs::File::open(path).await?;
+        let mut meter = Meter::new(0, file.metadata().await?.len());
+        let mut buffer = vec![0; 64 * 1024];
+
+        progress.send(meter.progress()).await;
+
+        loop {
+            let read = file.read(&mut buffer).await?;
+
+            if read == 0 {
+                break;
+            }
+
+            if let Some(update) = meter.advance(read as u64) {
+                progress.send(update).await;
+            }
+
+            writer.write_all(&buffer[..read]).await?;
+        }
+
+        if let Some(update) = meter.finish() {
+            progress.send(update).await;
+        }
+
+        writer.flush().await?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,130 @@
+//! Tests adding and removing backends of an installed build.
+#![cfg(all(
+    target_arch = "x86_64",
+    any(target_os = "linux", target_os = "windows")
+))]
+
+mod common;
+
+use common::archive;
+
+use llama_server::{Backend, Build, Error, Installer, Platform, Server, Source, backend, cpu};
+
+use std::fs;
+use std::path::Path;
+
+fn directories(path: &Path) -> Vec<String> {
+    let mut directories: Vec<_> = fs::read_dir(path)
+        .expect("read directory")
+        .map(|entry| entry.expect("read entry").file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+
+    directories.sort();
+    directories
+}
+
+#[tokio::test]
+async fn it_adds_and_removes_backends() -> Result<(), Error> {
+    let root = std::env::temp_dir().join(format!(
+        "llama-server-backends-{pid}",
+        pid = std::process::id()
+    ));
+
+    let _ = fs::remove_dir_all(&root);
+
+    let platform = Platform::current().expect("supported platform");
+    let build = Build::locked(100);
+    let releases = root.join("releases").join(build.to_string());
+
+    fs::create_dir_all(&releases)?;
+
+    archive(
+        &releases.join(format!("llama-server-{build}-{platform}.zip")),
+        &[platform.executable()],
+    );
+
+    for backend in ["hip", "vulkan"] {
+        archive(
+            &releases.join(format!("backend-{backend}-{build}-{platform}.zip")),
+            &[&format!("libggml-{backend}")],
+        );
+    }
+
+    // SAFETY: This is the only test of the binary, so nothing else reads
+    // the environment concurrently
+    #[allow(unsafe_code)]
+    unsafe {
+        std::env::set_var("LLAMA_SERVER_CACHE_DIR", root.join("cache"));
+    }
+
+    let installer =
+        Installer::new(Source::directory(root.join("releases"))).cpu(cpu::Variant::Portable);
+    let cache = root.join("cache").join(build.to_string());
+
+    let server = installer
+        .install(build, backend::Set::CPU | backend::Set::HIP)
+        .await?;
+
+    assert_eq!(server.backends, backend::Set::CPU | backend::Set::HIP);
+    assert_eq!(directories(&cache), ["backend-hip", "server", "server-hip"]);
+
+    let server = installer.add_backend(&server, Backend::Vulkan).await?;
+    let instance = cache.join("server-hip-vulkan");
+
+    assert_eq!(
+        server.backends,
+        backend::Set::CPU | backend::Set::HIP | backend::Set::VULKAN
+    );
+    assert_eq!(server.executable, instance.join(platform.executable()));
+    assert!(instance.join("libggml-vulkan").exists());
+    assert_eq!(
+        directories(&cache),
+        [
+            "backend-hip",
+            "backend-vulkan",
+            "server",
+            "server-hip-vulkan"
+        ]
+    );
+
+    assert!(matches!(
+        installer.add_backend(&server, Backend::Sycl).await,
+        Err(Error::ArtifactNotFound { .. })
+    ));
+    assert!(matches!(
+        installer.add_backend(&server, Backend::Metal).await,
+        Err(Error::UnsupportedBackend { .. })
+    ));
+    assert!(matches!(
+        installer.remove_backend(&server, Backend::Cpu).await,
+        Err(Error::BuiltinBackend { .. })
+    ));
+
+    let server = installer.remove_backend(&server, Backend::Hip).await?;
+
+    assert_eq!(server.backends, backend::Set::CPU | backend::Set::VULKAN);
+    assert_eq!(
+        server.executable,
+        cache.join("server-vulkan").join(platform.executable())
+    );
+    assert_eq!(
+        directories(&cache),
+        ["backend-vulkan", "server", "server-vulkan"]
+    );
+
+    let unknown = Server {
+        executable: root.join(platform.executable()),
+        ..server
+    };
+
+    assert!(matches!(
+        installer.remove_backend(&unknown, Backend::Vulkan).await,
+        Err(Error::InstanceNotFound { .. })
+    ));
+
+    fs::remove_dir_all(root)?;
+
+    Ok(())
+}
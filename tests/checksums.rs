@@ -0,0 +1,73 @@
+//! Tests the checksum verification of downloaded artifacts.
+#![cfg(all(
+    target_arch = "x86_64",
+    any(target_os = "linux", target_os = "windows")
+))]
+
+mod common;
+
+use common::archive;
+
+use llama_server::{Build, Error, Installer, Platform, Source, backend, cpu};
+
+use sha2::{Digest, Sha256};
+
+use std::fs;
+
+#[tokio::test]
+async fn it_verifies_checksums() -> Result<(), Error> {
+    let root = std::env::temp_dir().join(format!(
+        "llama-server-checksums-{pid}",
+        pid = std::process::id()
+    ));
+
+    let _ = fs::remove_dir_all(&root);
+
+    let platform = Platform::current().expect("supported platform");
+    let build = Build::locked(100);
+    let releases = root.join("releases").join(build.to_string());
+    let server = releases.join(format!("llama-server-{build}-{platform}.zip"));
+
+    fs::create_dir_all(&releases)?;
+    archive(&server, &[platform.executable()]);
+
+    // SAFETY: This is the only test of the binary, so nothing else reads
+    // the environment concurrently
+    #[allow(unsafe_code)]
+    unsafe {
+        std::env::set_var("LLAMA_SERVER_CACHE_DIR", root.join("cache"));
+    }
+
+    let installer =
+        Installer::new(Source::directory(root.join("releases"))).cpu(cpu::Variant::Portable);
+    let cache = root.join("cache").join(build.to_string());
+    let sidecar = server.with_extension("zip.sha256");
+
+    fs::write(&sidecar, format!("{}  server.zip\n", "0".repeat(64)))?;
+
+    assert!(matches!(
+        installer.install(build, backend::Set::CPU).await,
+        Err(Error::ChecksumMismatch { .. })
+    ));
+    assert!(!cache.join(".downloads").join("server.zip").exists());
+    assert!(!cache.join("server").exists());
+
+    let sha256: String = Sha256::digest(fs::read(&server)?)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    fs::write(&sidecar, format!("{sha256}  server.zip\n"))?;
+
+    let server = installer.install(build, backend::Set::CPU).await?;
+
+    assert_eq!(
+        server.executable,
+        cache.join("server").join(platform.executable())
+    );
+    assert!(server.executable.exists());
+
+    fs::remove_dir_all(root)?;
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+//! Helpers shared by the integration tests.
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes a zip archive at the given path containing the given files.
+///
+/// Each file contains its own name.
+pub fn archive(path: &Path, files: &[&str]) {
+    let mut writer = zip::ZipWriter::new(fs::File::create(path).expect("create archive"));
+
+    for file in files {
+        writer
+            .start_file(*file, zip::write::SimpleFileOptions::default())
+            .expect("start file");
+        writer.write_all(file.as_bytes()).expect("write file");
+    }
+
+    let _ = writer.finish().expect("finish archive");
+}
@@ -0,0 +1,74 @@
+//! Tests managing builds installed for foreign platforms.
+#![cfg(all(
+    target_arch = "x86_64",
+    any(target_os = "linux", target_os = "windows")
+))]
+
+mod common;
+
+use common::archive;
+
+use llama_server::{Build, Error, Installer, Platform, Server, Source, backend, cpu};
+
+use std::fs;
+
+#[tokio::test]
+async fn it_lists_and_deletes_foreign_builds() -> Result<(), Error> {
+    let root = std::env::temp_dir().join(format!(
+        "llama-server-platforms-{pid}",
+        pid = std::process::id()
+    ));
+
+    let _ = fs::remove_dir_all(&root);
+
+    let platform = Platform::current().expect("supported platform");
+    let foreign = Platform::MacosArm64;
+
+    for build in [Build::locked(100), Build::locked(200)] {
+        let releases = root.join("releases").join(build.to_string());
+
+        fs::create_dir_all(&releases)?;
+
+        for platform in [platform, foreign] {
+            archive(
+                &releases.join(format!("llama-server-{build}-{platform}.zip")),
+                &[platform.executable()],
+            );
+        }
+    }
+
+    // SAFETY: This is the only test of the binary, so nothing else reads
+    // the environment concurrently
+    #[allow(unsafe_code)]
+    unsafe {
+        std::env::set_var("LLAMA_SERVER_CACHE_DIR", root.join("cache"));
+    }
+
+    let installer =
+        Installer::new(Source::directory(root.join("releases"))).cpu(cpu::Variant::Portable);
+    let bundler = installer.clone().platform(foreign);
+
+    let _server = installer
+        .install(Build::locked(100), backend::Set::CPU)
+        .await?;
+
+    for build in [Build::locked(100), Build::locked(200)] {
+        let _server = bundler.install(build, backend::Set::CPU).await?;
+    }
+
+    assert_eq!(Server::list().await?, [Build::locked(100)]);
+    assert_eq!(installer.list().await?, [Build::locked(100)]);
+    assert_eq!(
+        bundler.list().await?,
+        [Build::locked(100), Build::locked(200)]
+    );
+
+    bundler.delete(Build::locked(100)).await?;
+
+    assert_eq!(Server::list().await?, [Build::locked(100)]);
+    assert_eq!(bundler.list().await?, [Build::locked(200)]);
+
+    fs::remove_dir_all(root)?;
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Tests the signature verification of downloaded artifacts.
+#![cfg(all(
+    target_arch = "x86_64",
+    any(target_os = "linux", target_os = "windows")
+))]
+
+mod common;
+
+use common::archive;
+
+use llama_server::{Build, Error, Installer, Platform, PublicKey, Source, backend, cpu};
+
+use std::fs;
+
+/// The public key of a throwaway minisign key pair.
+const PUBLIC_KEY: &str = "RWT4GRnfu4pChKPvg1R/PqaKvutNoc2SU+kOwFVO+ipf94IQXVu48Ho6";
+
+/// An empty zip archive, signed by [`PUBLIC_KEY`] with [`SIGNATURE`].
+const ARCHIVE: [u8; 22] = *b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+/// The minisign signature of [`ARCHIVE`].
+const SIGNATURE: &str = "untrusted comment: signature from test key
+RUT4GRnfu4pChGCLihmt5cKkwPrxQQr6pkWbcxatnEbtynbsJaMJHzZ3UyA5fY0lMx85HgvhOCRrqIop39LhnkZHWJ5bGrMmvgU=
+trusted comment: timestamp:0 file:llama-server.zip hashed
+uacM4Vg6zrwmSHta6lcozbS5Npa1JgA/ZJfrvQ91skXNphmuYHwYi6anwLnjJMLdmoOG4snhRcaLU1YvVg2XBA==
+";
+
+#[tokio::test]
+async fn it_verifies_signatures() -> Result<(), Error> {
+    let root = std::env::temp_dir().join(format!(
+        "llama-server-signatures-{pid}",
+        pid = std::process::id()
+    ));
+
+    let _ = fs::remove_dir_all(&root);
+
+    let platform = Platform::current().expect("supported platform");
+    let build = Build::locked(100);
+    let releases = root.join("releases").join(build.to_string());
+    let server = releases.join(format!("llama-server-{build}-{platform}.zip"));
+
+    fs::create_dir_all(&releases)?;
+    archive(&server, &[platform.executable()]);
+
+    // SAFETY: This is the only test of the binary, so nothing else reads
+    // the environment concurrently
+    #[allow(unsafe_code)]
+    unsafe {
+        std::env::set_var("LLAMA_SERVER_CACHE_DIR", root.join("cache"));
+    }
+
+    let installer = Installer::new(Source::directory(root.join("releases")))
+        .cpu(cpu::Variant::Portable)
+        .public_key(PublicKey::from_base64(PUBLIC_KEY).expect("valid public key"));
+    let cache = root.join("cache").join(build.to_string());
+    let download = cache.join(".downloads").join("server.zip");
+
+    assert!(matches!(
+        installer.install(build, backend::Set::CPU).await,
+        Err(Error::Unsigned { .. })
+    ));
+    assert!(!download.exists());
+
+    fs::write(server.with_extension("zip.minisig"), SIGNATURE)?;
+
+    assert!(matches!(
+        installer.install(build, backend::Set::CPU).await,
+        Err(Error::InvalidSignature { .. })
+    ));
+    assert!(!download.exists());
+    assert!(!cache.join("server").exists());
+
+    fs::write(&server, ARCHIVE)?;
+
+    let _server = installer.install(build, backend::Set::CPU).await?;
+
+    assert!(cache.join("server").exists());
+
+    fs::remove_dir_all(root)?;
+
+    Ok(())
+}